@@ -0,0 +1,132 @@
+use log::{info, warn};
+
+use crate::{client::{Client, Lecture}, data::Data, filter::LectureFilter, utils::config, Result};
+
+static RULES_KEY: &str = "lecture.auto_apply.rules";
+
+/// Merge the quota used by another run since `data` was loaded, e.g. `watch` next to a scheduled run,
+/// so that neither overwrites the applications of the other when saving
+pub fn merge_quota(data: &mut Data) {
+    let on_disk = Data::read_on_disk(&["auto_apply_date", "auto_apply_count"]);
+    let (Some(date), Some(count)) = (on_disk.get("auto_apply_date"), on_disk.get("auto_apply_count").and_then(|count| count.parse::<u32>().ok())) else {
+        return;
+    };
+    let current = data.get("auto_apply_count").and_then(|count| count.parse::<u32>().ok()).unwrap_or(0);
+    // dates are formatted as YYYY-MM-DD, so they compare as strings
+    match data.get("auto_apply_date").map(|current_date| date.as_str().cmp(current_date)) {
+        Some(std::cmp::Ordering::Less) => (),
+        Some(std::cmp::Ordering::Equal) => data.set("auto_apply_count", count.max(current).to_string()),
+        _ => {
+            data.set("auto_apply_date", date.clone());
+            data.set("auto_apply_count", count.to_string());
+        },
+    }
+}
+
+fn handled(data: &Data, key: &str) -> Vec<String> {
+    data.get(key).and_then(|handled| serde_json::from_str(handled).ok()).unwrap_or_default()
+}
+
+/// Record `lecture_id` as handled, so that the scheduled auto apply leaves it alone
+pub fn record_handled(data: &mut Data, lecture_id: &str) {
+    let mut handled = handled(data, "auto_apply_handled");
+    if !handled.iter().any(|id| id == lecture_id) {
+        handled.push(lecture_id.to_string());
+        data.set("auto_apply_handled", serde_json::to_string(&handled).unwrap());
+    }
+}
+
+/// Merge the lectures handled by another run since `data` was loaded.
+/// Lectures no longer listed are left out, like `run` prunes them.
+pub fn merge_handled(data: &mut Data) {
+    let on_disk = Data::read_on_disk(&["auto_apply_handled"]);
+    let on_disk: Vec<String> = on_disk.get("auto_apply_handled").and_then(|handled| serde_json::from_str(handled).ok()).unwrap_or_default();
+    let lectures: Vec<Lecture> = data.get("lectures").and_then(|lectures| serde_json::from_str(lectures).ok()).unwrap_or_default();
+    for id in on_disk {
+        if lectures.iter().any(|lecture| lecture.id == id) {
+            record_handled(data, &id);
+        }
+    }
+}
+
+/// Consume one application from today's quota. Returns `false` if `lecture.auto_apply.daily_cap` is reached.
+pub fn take_quota(data: &mut Data) -> bool {
+    merge_quota(data);
+    let daily_cap = config().get_int("lecture.auto_apply.daily_cap").unwrap_or(1).max(0) as u32;
+    let today = chrono::Local::now().date_naive().to_string();
+    let count = if data.get("auto_apply_date") == Some(today.as_str()) {
+        data.get("auto_apply_count").and_then(|count| count.parse().ok()).unwrap_or(0)
+    } else {
+        0
+    };
+    if count >= daily_cap {
+        warn!("Daily auto apply cap ({}) reached.", daily_cap);
+        return false;
+    }
+    data.set("auto_apply_date", today);
+    data.set("auto_apply_count", (count + 1).to_string());
+    true
+}
+
+/// Give back an application taken by `take_quota`, for applications rejected for now (e.g. not open yet)
+pub fn refund_quota(data: &mut Data) {
+    let count: u32 = data.get("auto_apply_count").and_then(|count| count.parse().ok()).unwrap_or(0);
+    data.set("auto_apply_count", count.saturating_sub(1).to_string());
+}
+
+/// Apply for lectures matching `lecture.auto_apply.rules`, at most `lecture.auto_apply.daily_cap` per day.
+/// In dry-run mode, lectures are only reported, and recorded apart from the handled ones so that they are
+/// still applied for once dry-run is turned off. Each lecture is handled or previewed at most once.
+pub async fn run(client: &Client, data: &mut Data, lectures: &[Lecture]) -> Result<()> {
+    if config().get_table(RULES_KEY).is_err() {
+        warn!("Auto apply enabled but `{}` not configured, skipping...", RULES_KEY);
+        return Ok(());
+    }
+    let filter = LectureFilter::load(RULES_KEY)?.fail_closed();
+    let dry_run = config().get_bool("lecture.auto_apply.dry_run").unwrap_or(true);
+
+    let handled_key = if dry_run { "auto_apply_previewed" } else { "auto_apply_handled" };
+    let mut handled = handled(data, handled_key);
+    handled.retain(|id| lectures.iter().any(|lecture| lecture.id == *id));
+    let candidates: Vec<&Lecture> = lectures.iter()
+        .filter(|lecture| !lecture.cancelled && !handled.contains(&lecture.id))
+        // full lectures are left to `watch`, instead of using up the quota every run
+        .filter(|lecture| lecture.remaining() != Some(0))
+        .filter(|lecture| filter.matches(lecture))
+        .collect();
+    if candidates.is_empty() {
+        info!("No lecture to apply for.");
+        return Ok(());
+    }
+
+    let mut results = Vec::new();
+    for lecture in candidates {
+        if dry_run {
+            info!("[dry run] Would apply for lecture(id = {}): {}", lecture.id, lecture.title);
+            results.push(format!("- {}（{}）：试运行，未实际报名", lecture.title, lecture.time));
+        } else {
+            if !take_quota(data) {
+                info!("Remaining lectures will be retried tomorrow.");
+                break;
+            }
+            let result = client.apply_lecture(lecture).await?;
+            if !result.is_final() {
+                info!("Auto apply for lecture(id = {}) rejected for now: {}, will be retried next time.", lecture.id, result.msg);
+                refund_quota(data);
+                continue;
+            }
+            let status = if result.success { "报名成功".to_string() } else { format!("报名失败：{}", result.msg) };
+            info!("Auto apply for lecture(id = {}): {}", lecture.id, status);
+            results.push(format!("- {}（{}）：{}", lecture.title, lecture.time, status));
+        }
+        handled.push(lecture.id.clone());
+        // saved right away, so that an error later in the loop does not apply for the same lecture again
+        data.set(handled_key, serde_json::to_string(&handled).unwrap());
+    }
+
+    if !results.is_empty() {
+        let title = if dry_run { "同济大讲堂自动报名（试运行）" } else { "同济大讲堂自动报名结果" };
+        client.send_message(title, &results.join("\n")).await?;
+    }
+    Ok(())
+}
\ No newline at end of file
@@ -0,0 +1,196 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use log::info;
+
+use crate::{client::{Client, Exam, Lecture}, constants, utils::config};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Failed to write calendar: {0}")]
+    IoError(#[from] std::io::Error),
+}
+
+type Result<T> = std::result::Result<T, Error>;
+
+pub enum EventTime {
+    AllDay(NaiveDate),
+    /// local time (UTC+8)
+    Range(NaiveDateTime, NaiveDateTime),
+}
+
+pub struct Event {
+    pub uid: String,
+    pub summary: String,
+    pub description: String,
+    pub location: String,
+    pub time: EventTime,
+    pub sequence: u32,
+    pub cancelled: bool,
+}
+
+pub fn lecture_uid(lecture: &Lecture) -> String {
+    format!("lecture-{}@seesubscriber", lecture.id)
+}
+
+pub fn exam_uid(exam: &Exam) -> String {
+    let uid = if exam.uid.is_empty() { exam.default_uid() } else { exam.uid.clone() };
+    format!("exam-{}@seesubscriber", uid)
+}
+
+impl Event {
+    pub fn from_lecture(lecture: &Lecture) -> Option<Event> {
+        let time = match lecture.time_range() {
+            Some((start, end)) => EventTime::Range(start, end),
+            None => EventTime::AllDay(Client::get_lecture_date(lecture)?),
+        };
+        Some(Event {
+            uid: lecture_uid(lecture),
+            summary: lecture.title.clone(),
+            description: format!("主讲人：{}\n级别：{}\n时间：{}", lecture.speaker, lecture.level, lecture.time),
+            location: lecture.place.clone(),
+            time,
+            sequence: lecture.sequence,
+            cancelled: lecture.cancelled,
+        })
+    }
+
+    pub fn from_exam(exam: &Exam) -> Option<Event> {
+        let (start, end) = exam.time_range()?;
+        Some(Event {
+            uid: exam_uid(exam),
+            summary: format!("考试：{}", exam.course_name),
+            description: if exam.seat.is_empty() { String::new() } else { format!("座位号：{}", exam.seat) },
+            location: exam.room.clone(),
+            time: EventTime::Range(start, end),
+            sequence: exam.sequence,
+            cancelled: false,
+        })
+    }
+
+    fn write_to(&self, lines: &mut Vec<String>, stamp: &str) {
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!("UID:{}", escape(&self.uid)));
+        lines.push(format!("DTSTAMP:{}", stamp));
+        match self.time {
+            EventTime::AllDay(date) => {
+                lines.push(format!("DTSTART;VALUE=DATE:{}", date.format("%Y%m%d")));
+                lines.push(format!("DTEND;VALUE=DATE:{}", (date + chrono::Duration::days(1)).format("%Y%m%d")));
+            },
+            EventTime::Range(start, end) => {
+                lines.push(format!("DTSTART:{}", to_utc(start)));
+                lines.push(format!("DTEND:{}", to_utc(end)));
+            }
+        }
+        lines.push(format!("SEQUENCE:{}", self.sequence));
+        lines.push(format!("STATUS:{}", if self.cancelled { "CANCELLED" } else { "CONFIRMED" }));
+        lines.push(format!("SUMMARY:{}", escape(&self.summary)));
+        if !self.description.is_empty() {
+            lines.push(format!("DESCRIPTION:{}", escape(&self.description)));
+        }
+        if !self.location.is_empty() {
+            lines.push(format!("LOCATION:{}", escape(&self.location)));
+        }
+        lines.push("END:VEVENT".to_string());
+    }
+}
+
+/// Convert local time (UTC+8, no DST) to iCalendar UTC form
+fn to_utc(time: NaiveDateTime) -> String {
+    (time - chrono::Duration::hours(8)).format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Fold content lines longer than 75 octets, without splitting UTF-8 characters
+fn fold(line: &str) -> String {
+    let mut result = String::new();
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            result.push_str("\r\n ");
+            width = 1;
+        }
+        result.push(c);
+        width += c.len_utf8();
+    }
+    result
+}
+
+fn render_calendar(properties: Vec<String>, events: &[&Event]) -> String {
+    let stamp = chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//SEESubscriber//SEESubscriber//ZH".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+    ];
+    lines.extend(properties);
+    for event in events {
+        event.write_to(&mut lines, &stamp);
+    }
+    lines.push("END:VCALENDAR".to_string());
+    lines.iter().map(|line| fold(line) + "\r\n").collect()
+}
+
+/// Render a complete VCALENDAR feed containing the given events
+pub fn render(name: &str, events: &[Event]) -> String {
+    let properties = vec![
+        "METHOD:PUBLISH".to_string(),
+        format!("X-WR-CALNAME:{}", escape(name)),
+        "X-WR-TIMEZONE:Asia/Shanghai".to_string(),
+    ];
+    render_calendar(properties, &events.iter().collect::<Vec<_>>())
+}
+
+/// Render a single event as a CalDAV calendar object resource (RFC 4791 forbids `METHOD` there)
+pub fn render_resource(event: &Event) -> String {
+    render_calendar(Vec::new(), &[event])
+}
+
+pub fn write(events: &[Event]) -> Result<()> {
+    let path = config().get_string("calendar.path").unwrap_or(constants::CALENDAR_DEFAULT_PATH.to_string());
+    std::fs::write(&path, render("SEESubscriber", events))?;
+    info!("{} event(s) written to calendar {}", events.len(), path);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_special_characters() {
+        assert_eq!(escape(r"a,b;c\d"), r"a\,b\;c\\d");
+        assert_eq!(escape("line1\r\nline2\nline3"), r"line1\nline2\nline3");
+        assert_eq!(escape("主讲人：张三"), "主讲人：张三");
+    }
+
+    #[test]
+    fn fold_short_line() {
+        assert_eq!(fold("SUMMARY:short"), "SUMMARY:short");
+    }
+
+    #[test]
+    fn fold_long_line() {
+        let line = "X".repeat(160);
+        let folded = fold(&line);
+        let parts: Vec<&str> = folded.split("\r\n").collect();
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[0].len(), 75);
+        assert!(parts[1..].iter().all(|part| part.starts_with(' ') && part.len() <= 75));
+        assert_eq!(folded.replace("\r\n ", ""), line);
+    }
+
+    #[test]
+    fn fold_keeps_utf8_characters_whole() {
+        let line = format!("SUMMARY:{}", "讲座".repeat(30));
+        let folded = fold(&line);
+        assert!(folded.split("\r\n").all(|part| part.len() <= 75));
+        assert_eq!(folded.replace("\r\n ", ""), line);
+    }
+}
\ No newline at end of file
@@ -1,18 +1,37 @@
 use std::sync::Arc;
 
 use log::{debug, info, warn};
-use reqwest::{cookie::Jar, header::{REFERER, USER_AGENT}, redirect, Url};
+use reqwest::{header::{REFERER, USER_AGENT}, redirect};
+use reqwest_cookie_store::CookieStoreMutex;
 use tokio::try_join;
 
-use crate::{constants, login, utils::config};
+use crate::{constants, credentials, login, redact, utils::{self, config}};
 
 mod notice_adapter;
+mod caldav;
+mod lecture_apply;
+mod my_lecture;
+mod timetable;
+mod grade;
+mod exam;
+mod message;
+mod selection;
+mod json_source;
+mod rss;
+mod session;
 
 pub use notice_adapter::*;
+pub use my_lecture::MyLecture;
+pub use exam::Exam;
+pub use timetable::CourseSession;
+pub use message::Message;
+pub use selection::{SelectionResult, SelectionRound};
+pub use session::Session;
 
 pub struct Client {
     client: reqwest::Client,
-    cookie_jar: Arc<Jar>,
+    cookie_jar: Arc<CookieStoreMutex>,
+    session: tokio::sync::Mutex<session::SessionState>,
 }
 
 pub struct Notice {
@@ -32,6 +51,56 @@ pub struct Lecture {
     pub id: String,
     #[serde(rename = "nameSpeaker")]
     pub speaker: String,
+    #[serde(rename = "lecturePlace", default, deserialize_with = "utils::deserialize_string")]
+    pub place: String,
+    #[serde(rename = "campusName", default, deserialize_with = "utils::deserialize_string")]
+    pub campus: String,
+    /// maximum number of attendees, `None` or 0 if unknown
+    #[serde(rename = "limitNum", default, deserialize_with = "utils::deserialize_count")]
+    pub capacity: Option<u32>,
+    #[serde(rename = "applyNum", default, deserialize_with = "utils::deserialize_count")]
+    pub applied: Option<u32>,
+    /// names of courses clashing with this lecture, filled in before pushing
+    #[serde(skip)]
+    pub conflicts: Vec<String>,
+    /// set when a previously seen lecture disappears from the lecture list
+    #[serde(default)]
+    pub cancelled: bool,
+    /// bumped every time the lecture is updated or cancelled, used as iCalendar `SEQUENCE`
+    #[serde(default)]
+    pub sequence: u32,
+}
+
+impl Lecture {
+    /// remaining seats, `None` if the capacity or the number of applications is unknown
+    pub fn remaining(&self) -> Option<u32> {
+        let capacity = self.capacity.filter(|capacity| *capacity > 0)?;
+        Some(capacity.saturating_sub(self.applied?))
+    }
+
+    /// title with a note about clashing courses, used in pushes
+    pub fn annotated_title(&self) -> String {
+        if self.conflicts.is_empty() {
+            return self.title.clone();
+        }
+        format!("{}（与{}冲突）", self.title, self.conflicts.join("、"))
+    }
+
+    fn same_content(&self, other: &Self) -> bool {
+        self.title == other.title
+            && self.level == other.level
+            && self.time == other.time
+            && self.speaker == other.speaker
+            && self.place == other.place
+            && self.campus == other.campus
+    }
+
+    /// Parse `lectureTime` into (start, end), falling back to a default duration when no end time is given.
+    /// Returns `None` if `lectureTime` has no parsable date or time.
+    pub fn time_range(&self) -> Option<(chrono::NaiveDateTime, chrono::NaiveDateTime)> {
+        Client::get_lecture_date(self)?;
+        utils::parse_time_range(&self.time, constants::LECTURE_DEFAULT_DURATION_MINUTES)
+    }
 }
 
 impl PartialEq for Lecture {
@@ -40,14 +109,25 @@ impl PartialEq for Lecture {
     }
 }
 
+/// Changes between the stored lecture list and the current one
+pub struct LectureDiff {
+    pub new: Vec<Lecture>,
+    pub updated: Vec<Lecture>,
+    pub cancelled: Vec<Lecture>,
+    /// all lectures not outdated yet, including cancelled ones
+    pub all: Vec<Lecture>,
+}
+
+/// Common response wrapper of 1.tongji.edu.cn APIs
 #[derive(serde::Deserialize)]
-struct LectureListVo {
+struct ResponseVo<T> {
     code: i32,
     msg: String,
-    data: Vec<Lecture>,
+    data: Option<T>,
 }
 
 #[derive(thiserror::Error, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
     #[error(transparent)]
     ReqwestError(#[from] reqwest::Error),
@@ -57,12 +137,22 @@ pub enum Error {
     LocalNotificationError(#[from] notify_rust::error::Error),
     #[error("No element found with selector: {0}")]
     ElementNotFound(String),
-    #[error("Failed to deserialize JSON: {0}. Text: {1}")]
+    #[error("Failed to deserialize JSON: {0}. Text: {}", redact::body(.1))]
     SerdeJsonError(serde_json::Error, String),
     #[error("Unknown error: {0}")]
     UnknownError(String),
     #[error(transparent)]
-    LoginError(#[from] login::Error)
+    LoginError(#[from] login::Error),
+    #[error("CalDAV request to {0} failed with status {1}")]
+    CalDavError(String, reqwest::StatusCode),
+    #[error("Failed to parse feed {0}: {1}")]
+    FeedParseError(String, feed_rs::parser::ParseFeedError),
+    #[error(transparent)]
+    CredentialError(#[from] credentials::Error),
+    #[error("Login skipped after {0} failed attempt(s), next attempt in {1} seconds")]
+    LoginThrottled(u32, i64),
+    #[error("Login suspended after {0} consecutive failures, fix `login.username` and `login.password` to resume")]
+    LoginSuspended(u32),
 }
 
 type Result<T> = std::result::Result<T, Error>;
@@ -73,7 +163,7 @@ impl Client {
         headers.insert(USER_AGENT, "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/127.0.0.0 Safari/537.36".parse().unwrap());
         headers.insert(REFERER, "https://see.tongji.edu.cn/index.htm".parse().unwrap());
 
-        let cookie_jar = Arc::new(Jar::default());
+        let cookie_jar = Arc::new(CookieStoreMutex::default());
 
         let client = reqwest::Client::builder()
             .default_headers(headers)
@@ -82,7 +172,7 @@ impl Client {
             .build()
             .unwrap();
 
-        Client { client, cookie_jar }
+        Client { client, cookie_jar, session: Default::default() }
     }
 
     pub async fn get_latest_notice_full_path(&self, sub_path: &str) -> Result<Notice> {
@@ -105,10 +195,10 @@ impl Client {
             })
     }
 
-    fn get_lecture_date(lecture: &Lecture) -> Option<chrono::NaiveDate> {
+    pub fn get_lecture_date(lecture: &Lecture) -> Option<chrono::NaiveDate> {
         let date= lecture.time.split(" ").next()
             .and_then(|date| chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").ok());
-        if date == None {
+        if date.is_none() {
             warn!("Failed to deserialize date `{}` in lecture(id = {})", lecture.time, lecture.id);
         }
         date
@@ -116,60 +206,89 @@ impl Client {
 
     async fn remove_outdated_lectures(old_lectures: Vec<Lecture>) -> Vec<Lecture> {
         let today = chrono::Local::now().date_naive();
-        old_lectures.into_iter().filter_map(|lecture| Client::get_lecture_date(&lecture).and_then(|date| Some((lecture, date))))
+        old_lectures.into_iter().filter_map(|lecture| Client::get_lecture_date(&lecture).map(|date| (lecture, date)))
             .filter(|(_, date)| *date >= today)
             .map(|(lecture, _)| lecture)
             .collect()
     }
 
-    /// return: (new, all_valid, sessionid)
-    pub async fn get_new_lectures(&self, old_lectures: Vec<Lecture>, session_id: &Option<&str>) -> Result<(Vec<Lecture>, Vec<Lecture>, String)> {
-        let old_lectures = Client::remove_outdated_lectures(old_lectures);
-        let url = config().get_string("lecture.url")?;
+    /// GET a 1.tongji.edu.cn url with the shared session, logging in again and retrying once if the request fails.
+    async fn get_authenticated(&self, url: &str, query: &[(&str, &str)]) -> Result<String> {
         let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
-        let username = config().get_string("login.username")?;
-        let password = config().get_string("login.password")?;
-
-        let mut new_session_id = match session_id {
-            Some(id) => {
-                info!("session id found in cache, login with it.");
-                self.cookie_jar.add_cookie_str(format!("sessionid={}", id).as_str(), &Url::parse("https://1.tongji.edu.cn/").unwrap());
-                id.to_string()
-            },
-            None => {
-                info!("session id not found in cache, login with username and password");
-                login::login(&self.client, &username, &password).await?
-            }
-        };
-
-        let mut response = self.client.get(&url)
+        self.ensure_session().await?;
+        let mut response = self.client.get(url)
             .header("Referer", "https://1.tongji.edu.cn/workbench")
             .query(&[("_t", timestamp)])
+            .query(query)
             .send().await?;
         if let Err(e) = response.error_for_status_ref() {
-            info!("Failed to get lecture list, re-login and retry. Msg: {}", e);
-            new_session_id = login::login(&self.client, &username, &password).await?;
-            response = self.client.get(&url)
+            info!("Failed to get {}, re-login and retry. Msg: {}", url, e);
+            self.renew_session().await?;
+            response = self.client.get(url)
                 .header("Referer", "https://1.tongji.edu.cn/workbench")
                 .query(&[("_t", timestamp)])
+                .query(query)
                 .send().await?;
         }
         let response = response.text().await?;
-        debug!("Lecture response: {}", response);
-        let lecture_list: LectureListVo = serde_json::from_str(&response).map_err(|e| Error::SerdeJsonError(e, response))?;
-        if lecture_list.code != 200 {
-            return Err(Error::UnknownError(lecture_list.msg));
+        debug!("Response of {}: {}", url, redact::body(&response));
+        Ok(response)
+    }
+
+    /// GET a 1.tongji.edu.cn API returning the common `{ code, msg, data }` wrapper, see `get_authenticated`.
+    async fn get_api<T: serde::de::DeserializeOwned>(&self, url: &str, query: &[(&str, &str)]) -> Result<T> {
+        let response = self.get_authenticated(url, query).await?;
+        let result: ResponseVo<T> = serde_json::from_str(&response).map_err(|e| Error::SerdeJsonError(e, response))?;
+        if result.code != 200 {
+            return Err(Error::UnknownError(result.msg));
         }
-        let current_lectures = lecture_list.data;
+        result.data.ok_or_else(|| Error::UnknownError(format!("No data in response of {}", url)))
+    }
+
+    pub async fn get_lectures(&self) -> Result<Vec<Lecture>> {
+        let url = config().get_string("lecture.url")?;
+        self.get_api(&url, &[]).await
+    }
+
+    pub async fn get_new_lectures(&self, old_lectures: Vec<Lecture>) -> Result<LectureDiff> {
+        let old_lectures = Client::remove_outdated_lectures(old_lectures);
+        let current_lectures = self.get_lectures().await?;
         let mut old_lectures = old_lectures.await;
         let mut new_lectures = Vec::new();
+        let mut updated_lectures = Vec::new();
+        let mut cancelled_lectures = Vec::new();
+        for old_lecture in old_lectures.iter_mut() {
+            if !old_lecture.cancelled && !current_lectures.contains(old_lecture) {
+                info!("Lecture(id = {}) disappeared from lecture list, marked as cancelled.", old_lecture.id);
+                old_lecture.cancelled = true;
+                old_lecture.sequence += 1;
+                cancelled_lectures.push(old_lecture.clone());
+            }
+        }
         for current_lecture in current_lectures {
-            if !old_lectures.contains(&current_lecture) {
-                old_lectures.push(current_lecture.clone());
-                new_lectures.push(current_lecture);
+            match old_lectures.iter_mut().find(|old| **old == current_lecture) {
+                Some(old_lecture) => {
+                    if old_lecture.cancelled || !old_lecture.same_content(&current_lecture) {
+                        info!("Lecture(id = {}) updated.", current_lecture.id);
+                        let sequence = old_lecture.sequence + 1;
+                        *old_lecture = current_lecture;
+                        old_lecture.sequence = sequence;
+                        updated_lectures.push(old_lecture.clone());
+                    }
+                },
+                None => {
+                    old_lectures.push(current_lecture.clone());
+                    new_lectures.push(current_lecture);
+                }
             }
         }
-        Ok((new_lectures, old_lectures, new_session_id))
+        let diff = LectureDiff {
+            new: new_lectures,
+            updated: updated_lectures,
+            cancelled: cancelled_lectures,
+            all: old_lectures,
+        };
+        Ok(diff)
     }
 
     pub async fn send_notice(&self, notice: &[Notice]) -> Result<()> {
@@ -188,6 +307,14 @@ impl Client {
         ).map(|_| ())
     }
 
+    pub async fn send_message(&self, title: &str, content: &str) -> Result<()> {
+        try_join!(
+            LocalAdapter::send_message(&self.client, title, content),
+            SCTAdapter::send_message(&self.client, title, content),
+            SC3Adapter::send_message(&self.client, title, content)
+        ).map(|_| ())
+    }
+
     pub async fn report_error(&self, message: &str, err: &crate::Error) -> Result<()> {
         try_join!(
             LocalAdapter::report_error(&self.client, message, err),
@@ -0,0 +1,81 @@
+use log::{debug, info};
+use reqwest::{header::CONTENT_TYPE, Method, RequestBuilder, StatusCode};
+
+use crate::{calendar::{self, Event}, credentials, utils::config};
+
+use super::{Client, Error, Lecture, LectureDiff, Result};
+
+impl Client {
+    async fn caldav_request(&self, method: Method, url: &str) -> Result<RequestBuilder> {
+        let username = credentials::get("caldav.username").await?;
+        let password = credentials::get("caldav.password").await?;
+        Ok(self.client.request(method, url).basic_auth(username, Some(password)))
+    }
+
+    async fn caldav_put(&self, base_url: &str, event: &Event) -> Result<()> {
+        let url = format!("{}{}.ics", base_url, event.uid);
+        debug!("CalDAV PUT {}", url);
+        let response = self.caldav_request(Method::PUT, &url).await?
+            .header(CONTENT_TYPE, "text/calendar; charset=utf-8")
+            .body(calendar::render_resource(event))
+            .send().await?;
+        if !response.status().is_success() {
+            return Err(Error::CalDavError(url, response.status()));
+        }
+        Ok(())
+    }
+
+    async fn caldav_delete(&self, base_url: &str, uid: &str) -> Result<()> {
+        let url = format!("{}{}.ics", base_url, uid);
+        debug!("CalDAV DELETE {}", url);
+        let response = self.caldav_request(Method::DELETE, &url).await?.send().await?;
+        if !response.status().is_success() && response.status() != StatusCode::NOT_FOUND {
+            return Err(Error::CalDavError(url, response.status()));
+        }
+        Ok(())
+    }
+
+    /// Create the collection if it doesn't exist yet. Servers answer 405 when it already exists.
+    async fn caldav_make_calendar(&self, base_url: &str) -> Result<()> {
+        let response = self.caldav_request(Method::from_bytes(b"MKCALENDAR").unwrap(), base_url).await?.send().await?;
+        let status = response.status();
+        if !status.is_success() && status != StatusCode::METHOD_NOT_ALLOWED && status != StatusCode::CONFLICT {
+            return Err(Error::CalDavError(base_url.to_string(), status));
+        }
+        Ok(())
+    }
+
+    /// Upload `events` to the configured CalDAV collection and delete the events with the `deleted` UIDs.
+    /// When `full_sync` is set, the collection is created first if needed.
+    pub async fn sync_caldav_events(&self, events: &[Event], deleted: &[String], full_sync: bool) -> Result<()> {
+        let mut base_url = config().get_string("caldav.url")?;
+        if !base_url.ends_with('/') {
+            base_url.push('/');
+        }
+        if full_sync {
+            info!("Performing full CalDAV sync to {}", base_url);
+            self.caldav_make_calendar(&base_url).await?;
+        }
+        for event in events {
+            self.caldav_put(&base_url, event).await?;
+        }
+        for uid in deleted {
+            self.caldav_delete(&base_url, uid).await?;
+        }
+        info!("CalDAV synced: {} event(s) created or updated, {} deleted.", events.len(), deleted.len());
+        Ok(())
+    }
+
+    /// Push lecture changes to the configured CalDAV collection.
+    /// When `full_sync` is set, every lecture still listed is uploaded instead of only the changed ones.
+    pub async fn sync_caldav(&self, diff: &LectureDiff, full_sync: bool) -> Result<()> {
+        let to_put: Vec<&Lecture> = if full_sync {
+            diff.all.iter().filter(|lecture| !lecture.cancelled).collect()
+        } else {
+            diff.new.iter().chain(diff.updated.iter()).filter(|lecture| !lecture.cancelled).collect()
+        };
+        let events: Vec<Event> = to_put.into_iter().filter_map(Event::from_lecture).collect();
+        let deleted: Vec<String> = diff.cancelled.iter().map(calendar::lecture_uid).collect();
+        self.sync_caldav_events(&events, &deleted, full_sync).await
+    }
+}
\ No newline at end of file
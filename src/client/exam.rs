@@ -0,0 +1,54 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{constants, utils::{self, config}};
+
+use super::{Client, Result};
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Exam {
+    /// ID of the arrangement, if the API provides one
+    #[serde(default, deserialize_with = "utils::deserialize_string")]
+    pub id: String,
+    #[serde(rename = "courseCode")]
+    pub course_code: String,
+    #[serde(rename = "courseName")]
+    pub course_name: String,
+    #[serde(rename = "examTime", default)]
+    pub time: String,
+    #[serde(rename = "roomName", default)]
+    pub room: String,
+    #[serde(rename = "seatNum", default, deserialize_with = "utils::deserialize_string")]
+    pub seat: String,
+    /// bumped every time the exam arrangement changes, used as iCalendar `SEQUENCE`
+    #[serde(default)]
+    pub sequence: u32,
+    /// identity of the exam kept across reschedules, used in the iCalendar `UID`
+    #[serde(default)]
+    pub uid: String,
+}
+
+impl Exam {
+    pub fn same_arrangement(&self, other: &Self) -> bool {
+        self.time == other.time && self.room == other.room && self.seat == other.seat
+    }
+
+    /// Identity of a newly seen exam: its ID, or else the course and the time,
+    /// since a course may have several exams (e.g. a midterm and a final)
+    pub fn default_uid(&self) -> String {
+        let key = if self.id.is_empty() { format!("{}-{}", self.course_code, self.time) } else { self.id.clone() };
+        key.chars().filter(|c| c.is_ascii_alphanumeric() || *c == '-').collect()
+    }
+
+    pub fn time_range(&self) -> Option<(chrono::NaiveDateTime, chrono::NaiveDateTime)> {
+        utils::parse_time_range(&self.time, constants::EXAM_DEFAULT_DURATION_MINUTES)
+    }
+}
+
+impl Client {
+    /// return: exams of the current term
+    pub async fn get_exams(&self) -> Result<Vec<Exam>> {
+        let (calendar_id, _) = self.get_current_term().await?;
+        let url = config().get_string("exam.url").unwrap_or(constants::EXAM_URL.to_string());
+        self.get_api(&url, &[("calendarId", &calendar_id)]).await
+    }
+}
\ No newline at end of file
@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{constants, utils::{self, config}};
+
+use super::{Client, Result};
+
+#[derive(Deserialize)]
+struct GradesVo {
+    #[serde(default)]
+    term: Vec<TermGradesVo>,
+}
+
+#[derive(Deserialize)]
+struct TermGradesVo {
+    #[serde(rename = "termName")]
+    term_name: String,
+    #[serde(rename = "creditInfo", default)]
+    grades: Vec<Grade>,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Grade {
+    #[serde(rename = "courseCode")]
+    pub course_code: String,
+    #[serde(rename = "courseName")]
+    pub course_name: String,
+    #[serde(rename = "score", default, deserialize_with = "utils::deserialize_string")]
+    pub score: String,
+    #[serde(default)]
+    pub term: String,
+}
+
+impl Grade {
+    /// stable identifier of a grade entry
+    pub fn key(&self) -> String {
+        format!("{}|{}", self.term, self.course_code)
+    }
+}
+
+impl Client {
+    /// return: grades of all terms
+    pub async fn get_grades(&self) -> Result<Vec<Grade>> {
+        let url = config().get_string("grade.url").unwrap_or(constants::GRADE_URL.to_string());
+        let grades = self.get_api::<GradesVo>(&url, &[]).await?;
+        let grades = grades.term.into_iter()
+            .flat_map(|term| {
+                let term_name = term.term_name;
+                term.grades.into_iter().map(move |grade| Grade { term: term_name.clone(), ..grade })
+            })
+            .collect();
+        Ok(grades)
+    }
+}
\ No newline at end of file
@@ -0,0 +1,19 @@
+use log::debug;
+
+use crate::redact;
+
+use super::{Client, Error, Result};
+
+impl Client {
+    /// GET an arbitrary JSON endpoint, with the shared 1.tongji.edu.cn session if `auth` is set.
+    pub async fn get_json(&self, url: &str, auth: bool) -> Result<serde_json::Value> {
+        let response = if auth {
+            self.get_authenticated(url, &[]).await?
+        } else {
+            let response = self.client.get(url).send().await?.error_for_status()?.text().await?;
+            debug!("Response of {}: {}", url, redact::body(&response));
+            response
+        };
+        serde_json::from_str(&response).map_err(|e| Error::SerdeJsonError(e, response))
+    }
+}
\ No newline at end of file
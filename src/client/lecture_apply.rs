@@ -0,0 +1,47 @@
+use log::{debug, info};
+
+use crate::{constants, redact, utils::config};
+
+use super::{Client, Error, Lecture, Result};
+
+/// messages of rejections that may turn into a success later, e.g. before registration opens or while full
+static TRANSIENT_REJECTIONS: [&str; 5] = ["未开放", "未开始", "已满", "繁忙", "稍后"];
+
+pub struct ApplyResult {
+    pub success: bool,
+    pub msg: String,
+}
+
+impl ApplyResult {
+    /// Whether the application is settled, i.e. succeeded or was rejected for good
+    pub fn is_final(&self) -> bool {
+        self.success || !TRANSIENT_REJECTIONS.iter().any(|keyword| self.msg.contains(keyword))
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct ApplyVo {
+    code: i32,
+    #[serde(default)]
+    msg: String,
+}
+
+impl Client {
+    /// Register for `lecture` with the shared session
+    pub async fn apply_lecture(&self, lecture: &Lecture) -> Result<ApplyResult> {
+        self.ensure_session().await?;
+        let url = config().get_string("lecture.auto_apply.url").unwrap_or(constants::LECTURE_APPLY_URL.to_string());
+        let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+        info!("Applying for lecture(id = {}): {}", lecture.id, lecture.title);
+        let response = self.client.post(&url)
+            .header("Referer", "https://1.tongji.edu.cn/workbench")
+            .query(&[("_t", timestamp)])
+            .json(&serde_json::json!({ "lectureId": lecture.id }))
+            .send().await?
+            .error_for_status()?
+            .text().await?;
+        debug!("Apply response: {}", redact::body(&response));
+        let result: ApplyVo = serde_json::from_str(&response).map_err(|e| Error::SerdeJsonError(e, response))?;
+        Ok(ApplyResult { success: result.code == 200, msg: result.msg })
+    }
+}
\ No newline at end of file
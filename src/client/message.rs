@@ -0,0 +1,35 @@
+use serde::Deserialize;
+
+use crate::{constants, utils::{self, config}};
+
+use super::{Client, Result};
+
+#[derive(Deserialize)]
+struct MessagePageVo {
+    #[serde(default)]
+    list: Vec<Message>,
+}
+
+/// A message in the 1.tongji.edu.cn message centre
+#[derive(Deserialize, Clone)]
+pub struct Message {
+    #[serde(deserialize_with = "utils::deserialize_string")]
+    pub id: String,
+    pub title: String,
+    #[serde(default, deserialize_with = "utils::deserialize_string")]
+    pub content: String,
+    #[serde(rename = "createTime", default, deserialize_with = "utils::deserialize_string")]
+    pub time: String,
+    #[serde(rename = "readStatus", default, deserialize_with = "utils::deserialize_flag")]
+    pub read: bool,
+}
+
+impl Client {
+    /// return: latest messages
+    pub async fn get_messages(&self) -> Result<Vec<Message>> {
+        let url = config().get_string("message.url").unwrap_or(constants::MESSAGE_URL.to_string());
+        let page_size = config().get_int("message.page_size").unwrap_or(20).to_string();
+        let page = self.get_api::<MessagePageVo>(&url, &[("pageNum", "1"), ("pageSize", &page_size)]).await?;
+        Ok(page.list)
+    }
+}
\ No newline at end of file
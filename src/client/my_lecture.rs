@@ -0,0 +1,19 @@
+use crate::{constants, utils::{self, config}};
+
+use super::{Client, Lecture, Result};
+
+/// A lecture the user registered for
+#[derive(serde::Deserialize, Clone)]
+pub struct MyLecture {
+    #[serde(flatten)]
+    pub lecture: Lecture,
+    #[serde(rename = "isAttend", default, deserialize_with = "utils::deserialize_flag")]
+    pub attended: bool,
+}
+
+impl Client {
+    pub async fn get_my_lectures(&self) -> Result<Vec<MyLecture>> {
+        let url = config().get_string("lecture.my_url").unwrap_or(constants::MY_LECTURE_URL.to_string());
+        self.get_api(&url, &[]).await
+    }
+}
\ No newline at end of file
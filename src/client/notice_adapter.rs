@@ -13,6 +13,8 @@ pub trait NoticeAdapter {
     async fn send_notice(client: &Client, notice: &[Notice]) -> super::Result<()>;
     async fn send_lecture(client: &Client, lecture: &[Lecture]) -> super::Result<()>;
     async fn report_error(client: &Client, message: &str, err: &crate::Error) -> super::Result<()>;
+    /// send a free-form message, `content` is markdown
+    async fn send_message(client: &Client, title: &str, content: &str) -> super::Result<()>;
 }
 
 fn is_configured(key: &str) -> bool {
@@ -36,7 +36,7 @@ impl NoticeAdapter for LocalAdapter {
 
     async fn send_lecture(_client: &Client, lecture: &[crate::client::Lecture]) -> Result<()> {
         let title = format!("找到{}条新的同济大讲堂", lecture.len());
-        let body = lecture.iter().map(|l| format!("- {}", l.title)).collect::<Vec<String>>().join("\n");
+        let body = lecture.iter().map(|l| format!("- {}", l.annotated_title())).collect::<Vec<String>>().join("\n");
         LocalAdapter::send_msg(&title, &body).await
     }
 
@@ -45,4 +45,8 @@ impl NoticeAdapter for LocalAdapter {
         let body = format!("{}\n\nError: {}", message, err);
         LocalAdapter::send_msg(title, &body).await
     }
+
+    async fn send_message(_client: &Client, title: &str, content: &str) -> Result<()> {
+        LocalAdapter::send_msg(title, content).await
+    }
 }
\ No newline at end of file
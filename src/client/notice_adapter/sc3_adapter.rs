@@ -15,8 +15,8 @@ impl SC3Adapter {
             info!("SC3Adapter not configured, skipping...");
             return Ok(());
         }
-        let sc3_key = crate::config().get_string("sc3.key");
-        if let Err(config::ConfigError::NotFound(_)) = sc3_key {
+        let sc3_key = crate::credentials::get("sc3.key").await;
+        if let Err(crate::credentials::Error::NotFound(_)) = sc3_key {
             info!("SC3 key not found, skipping...");
             return Ok(());
         }
@@ -32,7 +32,7 @@ impl SC3Adapter {
 
 impl NoticeAdapter for SC3Adapter {
     async fn send_notice(client: &Client, notice: &[Notice]) -> Result<()> {
-        if notice.len() == 0 {
+        if notice.is_empty() {
             info!("No new notice found, skipping...");
             return Ok(());
         }
@@ -42,11 +42,11 @@ impl NoticeAdapter for SC3Adapter {
     }
 
     async fn send_lecture(client: &Client, lecture: &[crate::client::Lecture]) -> crate::client::Result<()> {
-        if lecture.len() == 0 {
+        if lecture.is_empty() {
             info!("No new lecture found, skipping...");
             return Ok(());
         }
-        let desp = format!("|主题|级别|主讲人|时间|\n|:-:|:-:|:-:|:-:|\n{}", lecture.iter().map(|l| format!("|{}|{}|{}|{}|", l.title, l.level, l.speaker, l.time)).collect::<Vec<String>>().join("\n"));
+        let desp = format!("|主题|级别|主讲人|时间|\n|:-:|:-:|:-:|:-:|\n{}", lecture.iter().map(|l| format!("|{}|{}|{}|{}|", l.annotated_title(), l.level, l.speaker, l.time)).collect::<Vec<String>>().join("\n"));
         let short = format!(r#""{}"等{}条同济大讲堂"#, lecture[0].title, lecture.len());
         SC3Adapter::send_msg(client, "找到新的同济大讲堂", &desp, &short, "同济大学|同济大讲堂").await
     }
@@ -57,4 +57,8 @@ impl NoticeAdapter for SC3Adapter {
         let title = "SEESubscriber执行失败";
         SC3Adapter::send_msg(client, title, &desp, short, "告警").await
     }
+
+    async fn send_message(client: &Client, title: &str, content: &str) -> Result<()> {
+        SC3Adapter::send_msg(client, title, content, title, "同济大学").await
+    }
 }
\ No newline at end of file
@@ -15,8 +15,8 @@ impl SCTAdapter {
             info!("SCTAdapter not configured, skipping...");
             return Ok(());
         }
-        let sct_key = crate::config().get_string("sct.key");
-        if let Err(config::ConfigError::NotFound(_)) = sct_key {
+        let sct_key = crate::credentials::get("sct.key").await;
+        if let Err(crate::credentials::Error::NotFound(_)) = sct_key {
             info!("SCT key not found, skipping...");
             return Ok(());
         }
@@ -32,7 +32,7 @@ impl SCTAdapter {
 
 impl NoticeAdapter for SCTAdapter {
     async fn send_notice(client: &Client, notice: &[Notice]) -> Result<()> {
-        if notice.len() == 0 {
+        if notice.is_empty() {
             info!("No new notice found, skipping...");
             return Ok(());
         }
@@ -42,11 +42,11 @@ impl NoticeAdapter for SCTAdapter {
     }
 
     async fn send_lecture(client: &Client, lecture: &[crate::client::Lecture]) -> crate::client::Result<()> {
-        if lecture.len() == 0 {
+        if lecture.is_empty() {
             info!("No new lecture found, skipping...");
             return Ok(());
         }
-        let desp = format!("|主题|级别|主讲人|时间|\n|:-:|:-:|:-:|:-:|\n{}", lecture.iter().map(|l| format!("|{}|{}|{}|{}|", l.title, l.level, l.speaker, l.time)).collect::<Vec<String>>().join("\n"));
+        let desp = format!("|主题|级别|主讲人|时间|\n|:-:|:-:|:-:|:-:|\n{}", lecture.iter().map(|l| format!("|{}|{}|{}|{}|", l.annotated_title(), l.level, l.speaker, l.time)).collect::<Vec<String>>().join("\n"));
         let short = format!(r#""{}"等{}条同济大讲堂"#, lecture[0].title, lecture.len());
         SCTAdapter::send_msg(client, "找到新的同济大讲堂", &desp, &short).await
     }
@@ -57,4 +57,8 @@ impl NoticeAdapter for SCTAdapter {
         let title = "SEESubscriber执行失败";
         SCTAdapter::send_msg(client, title, &desp, short).await
     }
+
+    async fn send_message(client: &Client, title: &str, content: &str) -> Result<()> {
+        SCTAdapter::send_msg(client, title, content, title).await
+    }
 }
\ No newline at end of file
@@ -0,0 +1,22 @@
+use super::{Client, Error, Notice, Result};
+
+/// An entry of an RSS/Atom feed
+pub struct FeedEntry {
+    pub guid: String,
+    pub notice: Notice,
+}
+
+impl Client {
+    pub async fn get_feed_entries(&self, url: &str) -> Result<Vec<FeedEntry>> {
+        let response = self.client.get(url).send().await?.error_for_status()?.bytes().await?;
+        let feed = feed_rs::parser::parse(response.as_ref()).map_err(|e| Error::FeedParseError(url.to_string(), e))?;
+        Ok(feed.entries.into_iter().map(|entry| {
+            let link = entry.links.first().map(|link| link.href.clone()).unwrap_or_default();
+            let title = entry.title.map(|title| title.content).unwrap_or_else(|| link.clone());
+            FeedEntry {
+                guid: entry.id,
+                notice: Notice { title, url: link },
+            }
+        }).collect())
+    }
+}
\ No newline at end of file
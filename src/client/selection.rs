@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{constants, utils::{self, config}};
+
+use super::{Client, Result};
+
+/// A course selection round of the current term
+#[derive(Deserialize, Clone)]
+pub struct SelectionRound {
+    #[serde(deserialize_with = "utils::deserialize_string")]
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "beginTime", deserialize_with = "utils::deserialize_string")]
+    pub begin: String,
+    #[serde(rename = "endTime", deserialize_with = "utils::deserialize_string")]
+    pub end: String,
+}
+
+/// Selection result of one teaching class
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+pub struct SelectionResult {
+    #[serde(rename = "teachingClassCode")]
+    pub class_code: String,
+    #[serde(rename = "courseName")]
+    pub course_name: String,
+    #[serde(rename = "statusName", default, deserialize_with = "utils::deserialize_string")]
+    pub status: String,
+}
+
+impl Client {
+    /// return: selection rounds of the current term
+    pub async fn get_selection_rounds(&self) -> Result<Vec<SelectionRound>> {
+        let (calendar_id, _) = self.get_current_term().await?;
+        let url = config().get_string("selection.round_url").unwrap_or(constants::SELECTION_ROUND_URL.to_string());
+        self.get_api(&url, &[("calendarId", &calendar_id)]).await
+    }
+
+    /// return: selection results of the current term
+    pub async fn get_selection_results(&self) -> Result<Vec<SelectionResult>> {
+        let (calendar_id, _) = self.get_current_term().await?;
+        let url = config().get_string("selection.result_url").unwrap_or(constants::SELECTION_RESULT_URL.to_string());
+        self.get_api(&url, &[("calendarId", &calendar_id)]).await
+    }
+}
\ No newline at end of file
@@ -0,0 +1,285 @@
+use hmac::{Hmac, Mac};
+use log::{debug, info, warn};
+use rand::RngCore;
+use reqwest::Url;
+use reqwest_cookie_store::CookieStore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::{constants, credentials, login, utils::config};
+
+use super::{Client, Error, ResponseVo, Result};
+
+/// A 1.tongji.edu.cn session
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Session {
+    pub id: String,
+    /// unix timestamp of the login, `None` if unknown
+    pub login_at: Option<i64>,
+}
+
+impl Session {
+    /// Whether the session is about to expire according to `login.session_lifetime`
+    fn expiring(&self) -> bool {
+        let lifetime = config().get_int("login.session_lifetime").unwrap_or(constants::SESSION_DEFAULT_LIFETIME_SECS);
+        self.login_at.is_some_and(|login_at| chrono::Local::now().timestamp() - login_at >= lifetime - constants::SESSION_REFRESH_MARGIN_SECS)
+    }
+}
+
+/// Consecutive failed logins with the configured credentials
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct LoginFailures {
+    pub count: u32,
+    /// unix timestamp of the last failure
+    pub last_at: i64,
+    pub last_error: String,
+    /// fingerprint of the credentials that failed, the failures are forgotten when it changes
+    pub credentials: String,
+    /// whether the "credentials need attention" alert was sent
+    pub alerted: bool,
+    /// random key of the credentials fingerprint, kept when the failures are forgotten
+    #[serde(default)]
+    pub salt: String,
+}
+
+impl LoginFailures {
+    /// Seconds to wait before the next attempt, doubling with every failure
+    fn backoff(&self) -> i64 {
+        if self.count == 0 {
+            return 0;
+        }
+        let exponent = (self.count - 1).min(16);
+        (constants::LOGIN_BACKOFF_BASE_SECS << exponent).min(constants::LOGIN_BACKOFF_MAX_SECS)
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+impl LoginFailures {
+    /// Truncated HMAC of the credentials keyed with the random salt, so that it cannot be looked up in
+    /// precomputed tables. It is only meant to notice changed credentials.
+    fn fingerprint(&mut self, username: &str, password: &str) -> String {
+        if self.salt.is_empty() {
+            let mut salt = [0; 16];
+            rand::rngs::OsRng.fill_bytes(&mut salt);
+            self.salt = hex(&salt);
+        }
+        // HMAC accepts keys of any length
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.salt.as_bytes()).unwrap();
+        mac.update(format!("{}\n{}", username, password).as_bytes());
+        hex(&mac.finalize().into_bytes()[..4])
+    }
+
+    /// Forget the failures, e.g. of the previous credentials
+    fn reset(&mut self, credentials: String) {
+        *self = LoginFailures { credentials, salt: std::mem::take(&mut self.salt), ..Default::default() };
+    }
+}
+
+/// Session shared by all authenticated requests of a client
+#[derive(Default)]
+pub(super) struct SessionState {
+    session: Option<Session>,
+    /// whether the session is known to be logged in during this run
+    validated: bool,
+    failures: LoginFailures,
+}
+
+impl Client {
+    /// Use a session persisted by a previous run. It is probed before the first authenticated request.
+    /// A session already in use is kept.
+    pub async fn restore_session(&self, session: Session) {
+        let mut state = self.session.lock().await;
+        if state.session.is_some() {
+            return;
+        }
+        if let Err(e) = self.cookie_jar.lock().unwrap().parse(&format!("sessionid={}", session.id), &Url::parse("https://1.tongji.edu.cn/").unwrap()) {
+            warn!("Failed to restore session cookie: {}", e);
+        }
+        state.session = Some(session);
+        state.validated = false;
+    }
+
+    /// Use the failed logins recorded by a previous run
+    pub async fn restore_login_failures(&self, failures: LoginFailures) {
+        self.session.lock().await.failures = failures;
+    }
+
+    /// Failed logins so far, to be persisted
+    pub async fn login_failures(&self) -> LoginFailures {
+        self.session.lock().await.failures.clone()
+    }
+
+    /// The current session, to be persisted
+    pub async fn session(&self) -> Option<Session> {
+        self.session.lock().await.session.clone()
+    }
+
+    /// Restore the cookie jar serialized by `cookies`, dropping expired cookies.
+    /// Cookies of IAM let the single sign-on refresh the session without the password.
+    pub fn restore_cookies(&self, cookies: &str) {
+        match CookieStore::load_json(cookies.as_bytes()) {
+            Ok(store) => {
+                debug!("{} cookie(s) restored.", store.iter_unexpired().count());
+                *self.cookie_jar.lock().unwrap() = store;
+            },
+            Err(e) => warn!("Failed to restore cookies: {}", e),
+        }
+    }
+
+    /// Serialize the whole cookie jar, including domains, paths, expiry and session cookies
+    pub fn cookies(&self) -> String {
+        let mut buffer = Vec::new();
+        if let Err(e) = self.cookie_jar.lock().unwrap().save_incl_expired_and_nonpersistent_json(&mut buffer) {
+            warn!("Failed to serialize cookies: {}", e);
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+
+    /// Cheap check whether the session in the cookie jar is logged in
+    pub async fn is_logged_in(&self) -> Result<bool> {
+        let url = config().get_string("login.probe_url").unwrap_or(constants::SESSION_PROBE_URL.to_string());
+        let response = self.client.get(&url)
+            .header("Referer", "https://1.tongji.edu.cn/workbench")
+            .send().await?;
+        if !response.status().is_success() {
+            return Ok(false);
+        }
+        let text = response.text().await?;
+        Ok(serde_json::from_str::<ResponseVo<serde_json::Value>>(&text).is_ok_and(|result| result.code == 200))
+    }
+
+    /// Log in with the configured credentials, backing off exponentially after failures.
+    /// After `login.max_failures` consecutive failures, logins stop until the credentials change.
+    async fn login(&self, state: &mut SessionState) -> Result<()> {
+        let username = credentials::get("login.username").await?;
+        let password = credentials::get("login.password").await?;
+        let credentials = state.failures.fingerprint(&username, &password);
+        if state.failures.credentials != credentials {
+            if state.failures.count > 0 {
+                info!("Credentials changed, forgetting {} failed login(s).", state.failures.count);
+            }
+            state.failures.reset(credentials);
+        }
+        let max_failures = config().get_int("login.max_failures").unwrap_or(constants::LOGIN_DEFAULT_MAX_FAILURES).max(1) as u32;
+        if state.failures.count >= max_failures {
+            // retried on every run until sent
+            self.alert_suspended(&mut state.failures).await;
+            return Err(Error::LoginSuspended(state.failures.count));
+        }
+        let wait = state.failures.last_at + state.failures.backoff() - chrono::Local::now().timestamp();
+        if wait > 0 {
+            return Err(Error::LoginThrottled(state.failures.count, wait));
+        }
+
+        let result = self.login_as(state, &username, &password).await;
+        // only errors blaming the credentials count, others (network, maintenance, SMS, no captcha solver...) leave the failures as they are
+        let rejected = match &result {
+            Err(Error::LoginError(login::Error::WrongPassword(_) | login::Error::AccountLocked(_))) => true,
+            Err(Error::LoginError(login::Error::CaptchaRequired(attempts))) => *attempts >= constants::CAPTCHA_MAX_ATTEMPTS,
+            _ => false,
+        };
+        if let (true, Err(e)) = (rejected, &result) {
+            state.failures.count += 1;
+            state.failures.last_at = chrono::Local::now().timestamp();
+            state.failures.last_error = e.to_string();
+            warn!("Login failed {} time(s) in a row, next attempt in at least {} seconds.", state.failures.count, state.failures.backoff());
+            if state.failures.count >= max_failures {
+                self.alert_suspended(&mut state.failures).await;
+            }
+        }
+        result
+    }
+
+    /// Tell the user once that logging in stopped, until the credentials change
+    async fn alert_suspended(&self, failures: &mut LoginFailures) {
+        if failures.alerted {
+            return;
+        }
+        let content = format!("统一身份认证已连续登录失败{}次，已停止自动登录，请检查用户名和密码。修改后将自动恢复。\n\n最近一次错误：{}", failures.count, failures.last_error);
+        match self.send_message("同济大学登录凭据需要处理", &content).await {
+            Ok(()) => failures.alerted = true,
+            Err(e) => warn!("Failed to send the credentials alert: {}", e),
+        }
+    }
+
+    async fn login_as(&self, state: &mut SessionState, username: &str, password: &str) -> Result<()> {
+        let id = login::login(&self.client, username, password).await?;
+        state.session = Some(Session { id, login_at: Some(chrono::Local::now().timestamp()) });
+        state.validated = true;
+        let credentials = state.failures.fingerprint(username, password);
+        state.failures.reset(credentials);
+        Ok(())
+    }
+
+    /// Make sure the client has a logged in session, reusing the validated one if possible.
+    /// A cached session is probed once per run, and refreshed proactively when it is about to expire.
+    pub(super) async fn ensure_session(&self) -> Result<()> {
+        let mut state = self.session.lock().await;
+        match &state.session {
+            None => info!("No session cached, login with username and password."),
+            Some(session) if session.expiring() => info!("Session about to expire, refreshing..."),
+            Some(_) if state.validated => return Ok(()),
+            Some(_) => {
+                if self.is_logged_in().await? {
+                    debug!("Cached session still logged in.");
+                    state.validated = true;
+                    return Ok(());
+                }
+                info!("Cached session expired, login with username and password.");
+            },
+        }
+        self.login(&mut state).await
+    }
+
+    /// Log in with the given credentials instead of the configured ones
+    pub async fn login_with(&self, username: &str, password: &str) -> Result<()> {
+        let mut state = self.session.lock().await;
+        self.login_as(&mut state, username, password).await
+    }
+
+    /// Log in again after an authenticated request was rejected
+    pub(super) async fn renew_session(&self) -> Result<()> {
+        let mut state = self.session.lock().await;
+        self.login(&mut state).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failures(count: u32) -> LoginFailures {
+        LoginFailures { count, ..Default::default() }
+    }
+
+    #[test]
+    fn backoff_doubles_until_capped() {
+        assert_eq!(failures(0).backoff(), 0);
+        assert_eq!(failures(1).backoff(), constants::LOGIN_BACKOFF_BASE_SECS);
+        assert_eq!(failures(2).backoff(), constants::LOGIN_BACKOFF_BASE_SECS * 2);
+        assert_eq!(failures(3).backoff(), constants::LOGIN_BACKOFF_BASE_SECS * 4);
+        assert_eq!(failures(10).backoff(), constants::LOGIN_BACKOFF_MAX_SECS);
+        assert_eq!(failures(u32::MAX).backoff(), constants::LOGIN_BACKOFF_MAX_SECS);
+    }
+
+    #[test]
+    fn fingerprint_tells_credentials_apart() {
+        let mut failures = failures(0);
+        let fingerprint = failures.fingerprint("user", "password");
+        assert!(!failures.salt.is_empty());
+        assert_eq!(failures.fingerprint("user", "password"), fingerprint);
+        assert_ne!(failures.fingerprint("user", "password2"), fingerprint);
+        assert_ne!(LoginFailures::default().fingerprint("user", "password"), fingerprint);
+    }
+
+    #[test]
+    fn reset_keeps_salt() {
+        let mut failures = LoginFailures { count: 3, alerted: true, salt: "salt".to_string(), ..Default::default() };
+        failures.reset("fingerprint".to_string());
+        assert_eq!((failures.count, failures.alerted), (0, false));
+        assert_eq!((failures.credentials.as_str(), failures.salt.as_str()), ("fingerprint", "salt"));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,129 @@
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+
+use crate::{constants, utils::config};
+
+use super::{Client, Error, Lecture, Result};
+
+#[derive(Deserialize)]
+struct TermCalendarVo {
+    #[serde(rename = "schoolCalendar")]
+    school_calendar: SchoolCalendarVo,
+}
+
+#[derive(Deserialize)]
+struct SchoolCalendarVo {
+    id: i64,
+    /// first day of the term, milliseconds since epoch
+    #[serde(rename = "beginDay")]
+    begin_day: i64,
+}
+
+#[derive(Deserialize)]
+struct CourseVo {
+    #[serde(rename = "courseName")]
+    name: String,
+    #[serde(rename = "timeTableList", default)]
+    sessions: Vec<SessionVo>,
+}
+
+#[derive(Deserialize)]
+struct SessionVo {
+    #[serde(rename = "dayOfWeek")]
+    weekday: u32,
+    #[serde(rename = "timeStart")]
+    start_period: u32,
+    #[serde(rename = "timeEnd")]
+    end_period: u32,
+    #[serde(default)]
+    weeks: Vec<u32>,
+    #[serde(rename = "roomIdI18n", default)]
+    room: String,
+}
+
+/// One weekly class of a course
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct CourseSession {
+    pub course: String,
+    /// 1 = Monday, ..., 7 = Sunday
+    pub weekday: u32,
+    pub start_period: u32,
+    pub end_period: u32,
+    pub weeks: Vec<u32>,
+    pub room: String,
+}
+
+impl CourseSession {
+    /// clock time of the session, `None` if the periods are unknown
+    pub fn clock_time(&self) -> Option<(NaiveTime, NaiveTime)> {
+        let period = |index: u32| constants::CLASS_PERIODS.get(index.checked_sub(1)? as usize)
+            .map(|(start, end)| (NaiveTime::parse_from_str(start, "%H:%M").unwrap(), NaiveTime::parse_from_str(end, "%H:%M").unwrap()));
+        Some((period(self.start_period)?.0, period(self.end_period)?.1))
+    }
+}
+
+pub struct Timetable {
+    pub term_begin: NaiveDate,
+    pub sessions: Vec<CourseSession>,
+}
+
+impl Timetable {
+    /// teaching week of `date`, starting from 1
+    pub fn week_of(&self, date: NaiveDate) -> i64 {
+        (date - self.term_begin).num_days().div_euclid(7) + 1
+    }
+
+    /// sessions overlapping with the given time range
+    pub fn conflicts(&self, start: NaiveDateTime, end: NaiveDateTime) -> Vec<&CourseSession> {
+        let week = self.week_of(start.date());
+        let weekday = start.weekday().number_from_monday();
+        self.sessions.iter()
+            .filter(|session| session.weekday == weekday && session.weeks.iter().any(|w| *w as i64 == week))
+            .filter(|session| session.clock_time()
+                .is_some_and(|(session_start, session_end)| session_start < end.time() && start.time() < session_end))
+            .collect()
+    }
+
+    /// Fill `Lecture::conflicts` with the names of clashing courses
+    pub fn annotate(&self, lectures: &mut [Lecture]) {
+        for lecture in lectures {
+            if let Some((start, end)) = lecture.time_range() {
+                lecture.conflicts = self.conflicts(start, end).into_iter().map(|session| session.course.clone()).collect();
+            }
+        }
+    }
+}
+
+impl Client {
+    /// return: (calendar id, first day) of the current term
+    pub(super) async fn get_current_term(&self) -> Result<(String, NaiveDate)> {
+        let url = config().get_string("timetable.calendar_url").unwrap_or(constants::TERM_CALENDAR_URL.to_string());
+        let calendar = self.get_api::<TermCalendarVo>(&url, &[]).await?;
+        let term_begin = chrono::DateTime::from_timestamp_millis(calendar.school_calendar.begin_day)
+            .ok_or_else(|| Error::UnknownError(format!("Invalid term begin day: {}", calendar.school_calendar.begin_day)))?
+            .with_timezone(&chrono::FixedOffset::east_opt(8 * 3600).unwrap())
+            .date_naive();
+        Ok((calendar.school_calendar.id.to_string(), term_begin))
+    }
+
+    /// Get the timetable of the current term.
+    pub async fn get_timetable(&self) -> Result<Timetable> {
+        let (calendar_id, term_begin) = self.get_current_term().await?;
+        let url = config().get_string("timetable.url").unwrap_or(constants::TIMETABLE_URL.to_string());
+        let courses = self.get_api::<Vec<CourseVo>>(&url, &[("calendarId", &calendar_id)]).await?;
+        let sessions = courses.into_iter()
+            .flat_map(|course| {
+                let name = course.name;
+                course.sessions.into_iter().map(move |session| CourseSession {
+                    course: name.clone(),
+                    weekday: session.weekday,
+                    start_period: session.start_period,
+                    end_period: session.end_period,
+                    weeks: session.weeks,
+                    room: session.room,
+                })
+            })
+            .collect();
+        Ok(Timetable { term_begin, sessions })
+    }
+}
\ No newline at end of file
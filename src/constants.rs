@@ -1,2 +1,47 @@
 pub static DATA_PATH: &str = "./data.json";
-pub static NOTICE_SELECTOR: &str = "#line_u8_0 > a";
\ No newline at end of file
+pub static NOTICE_SELECTOR: &str = "#line_u8_0 > a";
+pub static LECTURE_DEFAULT_DURATION_MINUTES: i64 = 120;
+pub static CALENDAR_DEFAULT_PATH: &str = "./lectures.ics";
+pub static LECTURE_APPLY_URL: &str = "https://1.tongji.edu.cn/api/lectureservice/lectureHallApply/apply";
+pub static WATCH_DEFAULT_INTERVAL_SECS: i64 = 300;
+pub static WATCH_MIN_INTERVAL_SECS: i64 = 60;
+pub static MY_LECTURE_URL: &str = "https://1.tongji.edu.cn/api/lectureservice/lectureHallApply/myLectureList";
+pub static TERM_CALENDAR_URL: &str = "https://1.tongji.edu.cn/api/baseresservice/schoolCalendar/currentTermCalendar";
+pub static TIMETABLE_URL: &str = "https://1.tongji.edu.cn/api/electionservice/reportManagement/findStudentTimetab";
+/// (start, end) of each class period
+pub static CLASS_PERIODS: [(&str, &str); 12] = [
+    ("08:00", "08:45"), ("08:50", "09:35"), ("10:00", "10:45"), ("10:50", "11:35"),
+    ("13:30", "14:15"), ("14:20", "15:05"), ("15:25", "16:10"), ("16:15", "17:00"),
+    ("18:30", "19:15"), ("19:20", "20:05"), ("20:10", "20:55"), ("21:00", "21:45"),
+];
+pub static GRADE_URL: &str = "https://1.tongji.edu.cn/api/scoremanagementservice/scoreGrades/getMyGrades";
+pub static EXAM_URL: &str = "https://1.tongji.edu.cn/api/examservice/studentExam/findMyExamList";
+pub static EXAM_DEFAULT_DURATION_MINUTES: i64 = 120;
+pub static MESSAGE_URL: &str = "https://1.tongji.edu.cn/api/commonservice/messageCenter/findMyMessages";
+pub static SELECTION_ROUND_URL: &str = "https://1.tongji.edu.cn/api/electionservice/electionRound/findRoundList";
+pub static SELECTION_RESULT_URL: &str = "https://1.tongji.edu.cn/api/electionservice/student/getElectResult";
+pub static FEED_DEFAULT_DIR: &str = "./feeds";
+pub static FEED_DEFAULT_LISTEN: &str = "127.0.0.1:8080";
+/// number of items kept per feed source
+pub static FEED_MAX_ITEMS: usize = 50;
+pub static SMS_CODE_DEFAULT_PATH: &str = "./sms_code.txt";
+pub static SMS_CODE_DEFAULT_LISTEN: &str = "127.0.0.1:8765";
+pub static SMS_CODE_DEFAULT_TIMEOUT_SECS: i64 = 300;
+pub static CAPTCHA_DEFAULT_PATH: &str = "./captcha.png";
+pub static CAPTCHA_DEFAULT_ANSWER_PATH: &str = "./captcha.txt";
+pub static CAPTCHA_DEFAULT_TIMEOUT_SECS: i64 = 300;
+/// width in characters when drawing a captcha in the terminal
+pub static CAPTCHA_TERMINAL_WIDTH: u32 = 80;
+/// captcha answers tried before giving up a login
+pub static CAPTCHA_MAX_ATTEMPTS: u32 = 3;
+/// cheap 1.tongji.edu.cn API used to check whether a session is logged in
+pub static SESSION_PROBE_URL: &str = "https://1.tongji.edu.cn/api/sessionservice/session/getSessionUser";
+pub static SESSION_DEFAULT_LIFETIME_SECS: i64 = 7200;
+/// a session is refreshed this long before it expires
+pub static SESSION_REFRESH_MARGIN_SECS: i64 = 600;
+/// PBKDF2 rounds deriving the state encryption key from `state.passphrase`
+pub static STATE_PBKDF2_ROUNDS: u32 = 600_000;
+pub static LOGIN_DEFAULT_MAX_FAILURES: i64 = 5;
+/// wait after the first failed login, doubled after every further failure
+pub static LOGIN_BACKOFF_BASE_SECS: i64 = 300;
+pub static LOGIN_BACKOFF_MAX_SECS: i64 = 6 * 3600;
\ No newline at end of file
@@ -0,0 +1,147 @@
+use std::{collections::HashMap, process::Stdio, sync::Mutex};
+
+use log::debug;
+
+use crate::utils::config;
+
+#[derive(thiserror::Error, Debug)]
+#[allow(clippy::enum_variant_names)]
+pub enum Error {
+    #[error("Credential `{0}` not found, set it in config.yaml, the environment, a `_FILE`, a `_command` or the keyring (run with `login`)")]
+    NotFound(String),
+    #[error("Failed to read credential file: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("Credential command for `{0}` failed: {1}")]
+    CommandError(String, String),
+    #[error("Keyring error: {0}")]
+    KeyringError(#[from] keyring::Error),
+}
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Service name of the entries in the keyring
+static KEYRING_SERVICE: &str = "SEESubscriber";
+
+/// A place credentials can be read from. `key` is the config key of the credential, e.g. `login.password`.
+pub trait CredentialSource {
+    async fn get(key: &str) -> Result<Option<String>>;
+}
+
+/// The value in config.yaml or the `SEE_*` environment variable
+pub struct ConfigSource;
+
+impl CredentialSource for ConfigSource {
+    async fn get(key: &str) -> Result<Option<String>> {
+        Ok(config().get_string(key).ok())
+    }
+}
+
+/// A file named by `SEE_*_FILE` or `{key}_file`, e.g. a Docker or systemd secret
+pub struct FileSource;
+
+impl CredentialSource for FileSource {
+    async fn get(key: &str) -> Result<Option<String>> {
+        let variable = format!("SEE_{}_FILE", key.to_uppercase().replace('.', "_"));
+        let Some(path) = std::env::var(&variable).ok().or_else(|| config().get_string(&format!("{}_file", key)).ok()) else {
+            return Ok(None);
+        };
+        debug!("Reading `{}` from {}", key, path);
+        Ok(Some(tokio::fs::read_to_string(path).await?.trim().to_string()))
+    }
+}
+
+/// The stdout of `{key}_command`, e.g. `pass show tongji`
+pub struct CommandSource;
+
+impl CredentialSource for CommandSource {
+    async fn get(key: &str) -> Result<Option<String>> {
+        let Ok(command) = config().get_string(&format!("{}_command", key)) else {
+            return Ok(None);
+        };
+        debug!("Reading `{}` with command: {}", key, command);
+        let (shell, flag) = if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+        let output = tokio::process::Command::new(shell)
+            .args([flag, &command])
+            .stdin(Stdio::inherit())
+            .stderr(Stdio::inherit())
+            .output().await?;
+        if !output.status.success() {
+            return Err(Error::CommandError(key.to_string(), format!("exited with {}", output.status)));
+        }
+        let value = String::from_utf8(output.stdout).map_err(|e| Error::CommandError(key.to_string(), e.to_string()))?;
+        Ok(Some(value.trim().to_string()))
+    }
+}
+
+/// The system keyring (Secret Service, macOS Keychain or Windows Credential Manager), filled by the `login` command
+pub struct KeyringSource;
+
+impl CredentialSource for KeyringSource {
+    async fn get(key: &str) -> Result<Option<String>> {
+        match keyring_get(key).await {
+            Ok(value) => Ok(value),
+            Err(e) => {
+                // no keyring at all on most servers, so fall through to "not found"
+                debug!("Keyring unavailable for `{}`: {}", key, e);
+                Ok(None)
+            },
+        }
+    }
+}
+
+/// Read an entry from the keyring, `None` if it does not exist
+pub async fn keyring_get(name: &str) -> Result<Option<String>> {
+    let name = name.to_string();
+    // keyring calls must not run on a runtime thread, see the `keyring` docs
+    let result = tokio::task::spawn_blocking(move || keyring::Entry::new(KEYRING_SERVICE, &name)?.get_password()).await
+        .map_err(|e| Error::IoError(e.into()))?;
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Store an entry in the keyring
+pub async fn keyring_set(name: &str, value: &str) -> Result<()> {
+    let (name, value) = (name.to_string(), value.to_string());
+    tokio::task::spawn_blocking(move || keyring::Entry::new(KEYRING_SERVICE, &name)?.set_password(&value)).await
+        .map_err(|e| Error::IoError(e.into()))??;
+    Ok(())
+}
+
+fn cache() -> &'static Mutex<HashMap<String, String>> {
+    static CACHE: std::sync::OnceLock<Mutex<HashMap<String, String>>> = std::sync::OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+/// Use `value` for the credential `key` for the rest of the run
+pub fn remember(key: &str, value: &str) {
+    cache().lock().unwrap().insert(key.to_string(), value.to_string());
+}
+
+/// Credentials read so far, so that they can be redacted from logs
+pub fn resolved() -> Vec<String> {
+    cache().lock().unwrap().values().cloned().collect()
+}
+
+/// Get the credential `key` from the first source that has it: config, file, command, then keyring.
+/// Each credential is read once per run.
+pub async fn get(key: &str) -> Result<String> {
+    if let Some(value) = cache().lock().unwrap().get(key) {
+        return Ok(value.clone());
+    }
+    let mut value = ConfigSource::get(key).await?;
+    if value.is_none() {
+        value = FileSource::get(key).await?;
+    }
+    if value.is_none() {
+        value = CommandSource::get(key).await?;
+    }
+    if value.is_none() {
+        value = KeyringSource::get(key).await?;
+    }
+    let value = value.filter(|value| !value.is_empty()).ok_or_else(|| Error::NotFound(key.to_string()))?;
+    remember(key, &value);
+    Ok(value)
+}
\ No newline at end of file
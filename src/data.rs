@@ -6,7 +6,13 @@ use serde::{Deserialize, Serialize};
 use crate::constants;
 
 #[derive(Serialize, Deserialize)]
-pub struct Data(HashMap<String, String>);
+#[serde(transparent)]
+pub struct Data {
+    values: HashMap<String, String>,
+    /// keys written back when saving, `None` for all of them
+    #[serde(skip)]
+    owned_keys: Option<Vec<String>>,
+}
 
 #[derive(thiserror::Error, Debug)]
 enum Error {
@@ -19,7 +25,7 @@ enum Error {
 impl Data {
     pub fn load_or_default() -> Self {
         std::fs::File::open(constants::DATA_PATH)
-            .map_err(|err| Error::Io(err))
+            .map_err(Error::Io)
             .and_then(|file| {
                 let result = serde_json::from_reader::<_, Data>(file).map_err(|err| err.into());
                 if result.is_ok() {
@@ -29,21 +35,56 @@ impl Data {
             })
             .unwrap_or_else(|err| {
                 warn!("Failed to load data: {}. Creating empty data.", err);
-                Data(HashMap::new())
+                Data { values: HashMap::new(), owned_keys: None }
             })
     }
 
     pub fn set(&mut self, key: &str, value: String) {
-        self.0.insert(key.to_string(), value);
+        self.values.insert(key.to_string(), value);
+    }
+
+    pub fn remove(&mut self, key: &str) {
+        self.values.remove(key);
     }
 
     pub fn get<'a>(&'a self, key: &str) -> Option<&'a str> {
-        self.0.get(key).map(|s| s.as_str())
+        self.values.get(key).map(|s| s.as_str())
+    }
+
+    fn read_all_on_disk() -> HashMap<String, String> {
+        std::fs::File::open(constants::DATA_PATH).ok()
+            .and_then(|file| serde_json::from_reader(file).ok())
+            .unwrap_or_default()
+    }
+
+    /// Read `keys` from the file on disk, which another run may have changed since this data was loaded
+    pub fn read_on_disk(keys: &[&str]) -> HashMap<String, String> {
+        let mut values = Data::read_all_on_disk();
+        values.retain(|key, _| keys.contains(&key.as_str()));
+        values
+    }
+
+    /// Only write `keys` back when saving, on top of what other runs saved in the meantime.
+    /// For long-running processes sharing the file with scheduled runs.
+    pub fn own_only(&mut self, keys: &[&str]) {
+        self.owned_keys = Some(keys.iter().map(|key| key.to_string()).collect());
     }
 
     pub fn save(&self) {
+        let Some(keys) = &self.owned_keys else {
+            let file = std::fs::File::create(constants::DATA_PATH).unwrap();
+            serde_json::to_writer(file, self).unwrap();
+            return;
+        };
+        let mut values = Data::read_all_on_disk();
+        for key in keys {
+            match self.values.get(key) {
+                Some(value) => values.insert(key.clone(), value.clone()),
+                None => values.remove(key),
+            };
+        }
         let file = std::fs::File::create(constants::DATA_PATH).unwrap();
-        serde_json::to_writer(file, self).unwrap();
+        serde_json::to_writer(file, &values).unwrap();
     }
 }
 
@@ -0,0 +1,142 @@
+use aes_gcm::{aead::{Aead, AeadCore, KeyInit, OsRng}, Aes256Gcm, Key, Nonce};
+use base64::Engine;
+use log::info;
+use rand::RngCore;
+
+use crate::{constants, credentials, data::Data, utils::config};
+
+#[derive(thiserror::Error, Debug)]
+#[allow(clippy::enum_variant_names)]
+pub enum Error {
+    #[error("Unknown state encryption: {0}, expected none, keyring or passphrase")]
+    UnknownMethod(String),
+    #[error(transparent)]
+    CredentialError(#[from] credentials::Error),
+    #[error("Value is not encrypted")]
+    NotEncrypted,
+    #[error("Failed to decode encrypted value: {0}")]
+    DecodeError(#[from] base64::DecodeError),
+    #[error("Failed to decrypt value, the key or passphrase may have changed")]
+    DecryptError,
+    #[error("Decrypted value is not UTF-8: {0}")]
+    FromUtf8Error(#[from] std::string::FromUtf8Error),
+}
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// prefix of encrypted values, followed by the base64 of the nonce and the ciphertext
+static PREFIX: &str = "enc:v1:";
+/// keyring entry holding the generated key for the `keyring` method
+static KEYRING_KEY: &str = "state.key";
+
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(PREFIX)
+}
+
+/// Salt of the passphrase, generated once and kept in `data` since it is not secret
+fn salt(data: &mut Data) -> Vec<u8> {
+    let engine = base64::engine::general_purpose::STANDARD;
+    if let Some(salt) = data.get("state_salt").and_then(|salt| engine.decode(salt).ok()) {
+        return salt;
+    }
+    let mut salt = vec![0; 16];
+    rand::rngs::OsRng.fill_bytes(&mut salt);
+    data.set("state_salt", engine.encode(&salt));
+    salt
+}
+
+/// The key configured by `state.encryption`, `None` if sensitive state is kept in plain text.
+/// It is derived once per process, as the passphrase takes a noticeable time to stretch.
+pub async fn key(data: &mut Data) -> Result<Option<Key<Aes256Gcm>>> {
+    static KEY: tokio::sync::OnceCell<Option<Key<Aes256Gcm>>> = tokio::sync::OnceCell::const_new();
+    KEY.get_or_try_init(|| derive_key(data)).await.copied()
+}
+
+async fn derive_key(data: &mut Data) -> Result<Option<Key<Aes256Gcm>>> {
+    let method = config().get_string("state.encryption").unwrap_or("none".to_string());
+    let engine = base64::engine::general_purpose::STANDARD;
+    match method.to_lowercase().as_str() {
+        "none" => Ok(None),
+        "keyring" => {
+            if let Some(key) = credentials::keyring_get(KEYRING_KEY).await?.and_then(|key| engine.decode(key).ok()).filter(|key| key.len() == 32) {
+                return Ok(Some(*Key::<Aes256Gcm>::from_slice(&key)));
+            }
+            info!("Generating state encryption key in the keyring...");
+            let key = Aes256Gcm::generate_key(OsRng);
+            credentials::keyring_set(KEYRING_KEY, &engine.encode(key)).await?;
+            Ok(Some(key))
+        },
+        "passphrase" => {
+            let passphrase = credentials::get("state.passphrase").await?;
+            let salt = salt(data);
+            // hundreds of thousands of rounds would stall the runtime thread
+            let key = tokio::task::spawn_blocking(move || pbkdf2::pbkdf2_hmac_array::<sha2::Sha256, 32>(passphrase.as_bytes(), &salt, constants::STATE_PBKDF2_ROUNDS)).await
+                .map_err(|e| credentials::Error::IoError(e.into()))?;
+            Ok(Some(key.into()))
+        },
+        _ => Err(Error::UnknownMethod(method)),
+    }
+}
+
+pub fn encrypt(key: &Key<Aes256Gcm>, plaintext: &str) -> String {
+    let nonce = Aes256Gcm::generate_nonce(OsRng);
+    // encryption with a fresh nonce only fails for messages of gigabytes
+    let ciphertext = Aes256Gcm::new(key).encrypt(&nonce, plaintext.as_bytes()).unwrap();
+    format!("{}{}", PREFIX, base64::engine::general_purpose::STANDARD.encode([nonce.as_slice(), &ciphertext].concat()))
+}
+
+pub fn decrypt(key: &Key<Aes256Gcm>, value: &str) -> Result<String> {
+    let encoded = value.strip_prefix(PREFIX).ok_or(Error::NotEncrypted)?;
+    let bytes = base64::engine::general_purpose::STANDARD.decode(encoded)?;
+    if bytes.len() < 12 {
+        return Err(Error::DecryptError);
+    }
+    let (nonce, ciphertext) = bytes.split_at(12);
+    let plaintext = Aes256Gcm::new(key).decrypt(Nonce::from_slice(nonce), ciphertext).map_err(|_| Error::DecryptError)?;
+    Ok(String::from_utf8(plaintext)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let key = Aes256Gcm::generate_key(OsRng);
+        let encrypted = encrypt(&key, "sessionid=abc;中文");
+        assert!(is_encrypted(&encrypted));
+        assert!(!encrypted.contains("abc"));
+        assert_eq!(decrypt(&key, &encrypted).unwrap(), "sessionid=abc;中文");
+    }
+
+    #[test]
+    fn fresh_nonce() {
+        let key = Aes256Gcm::generate_key(OsRng);
+        assert_ne!(encrypt(&key, "value"), encrypt(&key, "value"));
+    }
+
+    #[test]
+    fn rejects_tampering() {
+        let key = Aes256Gcm::generate_key(OsRng);
+        let engine = base64::engine::general_purpose::STANDARD;
+        let mut bytes = engine.decode(encrypt(&key, "value").strip_prefix(PREFIX).unwrap()).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        let tampered = format!("{}{}", PREFIX, engine.encode(&bytes));
+        assert!(matches!(decrypt(&key, &tampered), Err(Error::DecryptError)));
+    }
+
+    #[test]
+    fn rejects_wrong_key() {
+        let encrypted = encrypt(&Aes256Gcm::generate_key(OsRng), "value");
+        assert!(matches!(decrypt(&Aes256Gcm::generate_key(OsRng), &encrypted), Err(Error::DecryptError)));
+    }
+
+    #[test]
+    fn rejects_malformed() {
+        let key = Aes256Gcm::generate_key(OsRng);
+        assert!(matches!(decrypt(&key, "plain"), Err(Error::NotEncrypted)));
+        assert!(matches!(decrypt(&key, "enc:v1:!!"), Err(Error::DecodeError(_))));
+        assert!(matches!(decrypt(&key, "enc:v1:AAAA"), Err(Error::DecryptError)));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,132 @@
+use log::info;
+
+use crate::{calendar::{self, Event}, client::{Client, Exam}, data::Data, utils::config, Result};
+
+fn describe(exam: &Exam) -> String {
+    let mut description = format!("{}：{}", exam.course_name, exam.time);
+    if !exam.room.is_empty() {
+        description += &format!("，{}", exam.room);
+    }
+    if !exam.seat.is_empty() {
+        description += &format!("，座位号{}", exam.seat);
+    }
+    description
+}
+
+/// Find the previous arrangement of `exam` among `old_exams`: the one with the same ID or the same course and time,
+/// or else the only one of the same course that is gone from `exams`, which then has been rescheduled
+fn previous<'a>(old_exams: &'a [Exam], exams: &[Exam], exam: &Exam) -> Option<&'a Exam> {
+    let same = |a: &Exam, b: &Exam| if a.id.is_empty() || b.id.is_empty() {
+        a.course_code == b.course_code && a.time == b.time
+    } else {
+        a.id == b.id
+    };
+    if let Some(old) = old_exams.iter().find(|old| same(old, exam)) {
+        return Some(old);
+    }
+    let unmatched_old: Vec<&Exam> = old_exams.iter()
+        .filter(|old| old.course_code == exam.course_code && !exams.iter().any(|current| same(old, current)))
+        .collect();
+    let unmatched_new = exams.iter()
+        .filter(|current| current.course_code == exam.course_code && !old_exams.iter().any(|old| same(old, current)))
+        .count();
+    match unmatched_old[..] {
+        [old] if unmatched_new == 1 => Some(old),
+        _ => None,
+    }
+}
+
+pub fn load(data: &Data) -> Vec<Exam> {
+    data.get("exams").and_then(|exams| serde_json::from_str(exams).ok()).unwrap_or_default()
+}
+
+/// Push new and changed exam arrangements, and remind of exams starting within `exam.remind_before_hours`
+pub async fn run(client: &Client, data: &mut Data) -> Result<()> {
+    info!("Getting exams...");
+    let exams = client.get_exams().await?;
+
+    let old_exams = load(data);
+    let mut new_exams = Vec::new();
+    let mut changed_exams = Vec::new();
+    let mut updated = Vec::new();
+    let exams: Vec<Exam> = exams.iter().cloned().map(|mut exam| {
+        let old = previous(&old_exams, &exams, &exam);
+        match old {
+            None => new_exams.push(describe(&exam)),
+            Some(old) if !old.same_arrangement(&exam) => {
+                exam.sequence = old.sequence + 1;
+                changed_exams.push(format!("{}（原：{}）", describe(&exam), describe(old)));
+            },
+            Some(old) => exam.sequence = old.sequence,
+        }
+        exam.uid = old.map(|old| old.uid.clone()).filter(|uid| !uid.is_empty()).unwrap_or_else(|| exam.default_uid());
+        if old.is_none_or(|old| !old.same_arrangement(&exam)) {
+            updated.push(exam.uid.clone());
+        }
+        exam
+    }).collect();
+    data.set("exams", serde_json::to_string(&exams).unwrap());
+    info!("{} new exam(s), {} changed exam(s) found.", new_exams.len(), changed_exams.len());
+
+    if !new_exams.is_empty() || !changed_exams.is_empty() {
+        let mut content = Vec::new();
+        if !new_exams.is_empty() {
+            content.push(format!("## 新的考试安排\n\n{}", new_exams.iter().map(|e| format!("- {}", e)).collect::<Vec<String>>().join("\n")));
+        }
+        if !changed_exams.is_empty() {
+            content.push(format!("## 变更的考试安排\n\n{}", changed_exams.iter().map(|e| format!("- {}", e)).collect::<Vec<String>>().join("\n")));
+        }
+        client.send_message("考试安排有更新", &content.join("\n\n")).await?;
+    }
+
+    if config().get_bool("caldav.enabled").is_ok_and(|v| v) {
+        sync_caldav(client, data, &old_exams, &exams, &updated).await?;
+    }
+
+    remind(client, data, &exams).await
+}
+
+/// Push new and changed exams to CalDAV, and delete the ones no longer listed
+async fn sync_caldav(client: &Client, data: &mut Data, old_exams: &[Exam], exams: &[Exam], updated: &[String]) -> Result<()> {
+    let url = config().get_string("caldav.url")?;
+    let full_sync = data.get("caldav_exams_synced") != Some(url.as_str());
+    let events: Vec<Event> = exams.iter()
+        .filter(|exam| full_sync || updated.contains(&exam.uid))
+        .filter_map(Event::from_exam)
+        .collect();
+    let uids: Vec<String> = exams.iter().map(calendar::exam_uid).collect();
+    let deleted: Vec<String> = old_exams.iter().map(calendar::exam_uid).filter(|uid| !uids.contains(uid)).collect();
+    if let Err(e) = client.sync_caldav_events(&events, &deleted, full_sync).await {
+        // `exams` is already saved, so the changes are gone: sync everything next time
+        data.remove("caldav_exams_synced");
+        return Err(e.into());
+    }
+    data.set("caldav_exams_synced", url);
+    Ok(())
+}
+
+async fn remind(client: &Client, data: &mut Data, exams: &[Exam]) -> Result<()> {
+    let hours = config().get_int("exam.remind_before_hours").unwrap_or(0);
+    if hours <= 0 {
+        return Ok(());
+    }
+    let now = chrono::Local::now().naive_local();
+    // keyed by course and time, so a rescheduled exam is reminded again
+    let mut reminded: Vec<String> = data.get("exam_reminded")
+        .and_then(|reminded| serde_json::from_str(reminded).ok())
+        .unwrap_or_default();
+    let key = |exam: &Exam| format!("{}|{}", exam.course_code, exam.time);
+    reminded.retain(|k| exams.iter().any(|exam| key(exam) == *k));
+    let upcoming: Vec<&Exam> = exams.iter()
+        .filter(|exam| !reminded.contains(&key(exam)))
+        .filter(|exam| exam.time_range().is_some_and(|(start, _)| start > now && start - now <= chrono::Duration::hours(hours)))
+        .collect();
+    if !upcoming.is_empty() {
+        info!("Reminding {} upcoming exam(s)...", upcoming.len());
+        let content = upcoming.iter().map(|exam| format!("- {}", describe(exam))).collect::<Vec<String>>().join("\n");
+        client.send_message("考试提醒", &content).await?;
+        reminded.extend(upcoming.iter().map(|exam| key(exam)));
+    }
+    data.set("exam_reminded", serde_json::to_string(&reminded).unwrap());
+    Ok(())
+}
\ No newline at end of file
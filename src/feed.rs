@@ -0,0 +1,170 @@
+use std::{collections::BTreeMap, path::Path};
+
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use tokio::{io::{AsyncReadExt, AsyncWriteExt}, net::TcpListener};
+
+use crate::{client::{Lecture, Notice}, constants, data::Data, utils::config};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Failed to write or serve feed: {0}")]
+    IoError(#[from] std::io::Error),
+}
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// A collected notice or lecture, kept in `Data` to generate feeds from
+#[derive(Serialize, Deserialize, Clone)]
+pub struct FeedItem {
+    pub source: String,
+    pub id: String,
+    pub title: String,
+    pub link: String,
+    pub content: String,
+    /// RFC 3339
+    pub updated: String,
+}
+
+impl FeedItem {
+    pub fn from_notice(source: &str, notice: &Notice) -> Self {
+        FeedItem {
+            source: source.to_string(),
+            id: notice.url.clone(),
+            title: notice.title.clone(),
+            link: notice.url.clone(),
+            content: String::new(),
+            updated: chrono::Local::now().to_rfc3339(),
+        }
+    }
+
+    pub fn from_lecture(lecture: &Lecture) -> Self {
+        FeedItem {
+            source: "lecture".to_string(),
+            id: format!("urn:seesubscriber:lecture:{}", lecture.id),
+            title: lecture.title.clone(),
+            link: String::new(),
+            content: format!("主讲人：{}\n级别：{}\n时间：{}", lecture.speaker, lecture.level, lecture.time),
+            updated: chrono::Local::now().to_rfc3339(),
+        }
+    }
+}
+
+fn load(data: &Data) -> Vec<FeedItem> {
+    data.get("feed_items").and_then(|items| serde_json::from_str(items).ok()).unwrap_or_default()
+}
+
+/// Append items to the feed history, keeping the latest `constants::FEED_MAX_ITEMS` per source
+pub fn record(data: &mut Data, new_items: Vec<FeedItem>) {
+    if new_items.is_empty() {
+        return;
+    }
+    let mut items = load(data);
+    items.extend(new_items);
+    let mut count: BTreeMap<String, usize> = BTreeMap::new();
+    let mut kept: Vec<FeedItem> = items.into_iter().rev()
+        .filter(|item| {
+            let count = count.entry(item.source.clone()).or_default();
+            *count += 1;
+            *count <= constants::FEED_MAX_ITEMS
+        })
+        .collect();
+    kept.reverse();
+    data.set("feed_items", serde_json::to_string(&kept).unwrap());
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// File name of a source's feed, keeping only alphanumeric characters
+fn file_name(source: &str) -> String {
+    let name: String = source.chars().map(|c| if c.is_alphanumeric() { c } else { '_' }).collect();
+    format!("{}.xml", name)
+}
+
+fn render(id: &str, title: &str, items: &[&FeedItem]) -> String {
+    let updated = items.iter().map(|item| item.updated.as_str()).max()
+        .map(str::to_string)
+        .unwrap_or_else(|| chrono::Local::now().to_rfc3339());
+    let entries = items.iter().rev().map(|item| {
+        let link = if item.link.is_empty() { String::new() } else { format!("\n    <link href=\"{}\"/>", escape(&item.link)) };
+        let content = if item.content.is_empty() { String::new() } else { format!("\n    <content type=\"text\">{}</content>", escape(&item.content)) };
+        format!("  <entry>\n    <id>{}</id>\n    <title>{}</title>\n    <updated>{}</updated>{}{}\n  </entry>\n",
+            escape(&item.id), escape(&item.title), item.updated, link, content)
+    }).collect::<String>();
+    format!("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<feed xmlns=\"http://www.w3.org/2005/Atom\">\n  <id>urn:seesubscriber:{}</id>\n  <title>{}</title>\n  <updated>{}</updated>\n  <author><name>SEESubscriber</name></author>\n{}</feed>",
+        escape(id), escape(title), updated, entries)
+}
+
+fn feed_dir() -> String {
+    config().get_string("feed.dir").unwrap_or(constants::FEED_DEFAULT_DIR.to_string())
+}
+
+/// Write one feed per source plus `all.xml` to `feed.dir`
+pub fn write(data: &Data) -> Result<()> {
+    let dir = feed_dir();
+    std::fs::create_dir_all(&dir)?;
+    let items = load(data);
+    let mut sources: BTreeMap<&str, Vec<&FeedItem>> = BTreeMap::new();
+    for item in &items {
+        sources.entry(item.source.as_str()).or_default().push(item);
+    }
+    for (source, items) in &sources {
+        std::fs::write(Path::new(&dir).join(file_name(source)), render(source, &format!("SEESubscriber - {}", source), items))?;
+    }
+    std::fs::write(Path::new(&dir).join("all.xml"), render("all", "SEESubscriber", &items.iter().collect::<Vec<_>>()))?;
+    info!("{} feed(s) written to {}", sources.len() + 1, dir);
+    Ok(())
+}
+
+/// Find the file for a request path. Only plain file names inside `feed.dir` and the calendar file are served.
+fn resolve(path: &str) -> Option<(String, &'static str)> {
+    let name = path.trim_start_matches('/');
+    if name.is_empty() || name.contains('/') || name.contains('\\') || name.starts_with('.') {
+        return None;
+    }
+    let calendar = config().get_string("calendar.path").unwrap_or(constants::CALENDAR_DEFAULT_PATH.to_string());
+    if Path::new(&calendar).file_name().is_some_and(|file_name| file_name == name) {
+        return Some((calendar, "text/calendar; charset=utf-8"));
+    }
+    name.ends_with(".xml").then(|| (Path::new(&feed_dir()).join(name).to_string_lossy().to_string(), "application/atom+xml; charset=utf-8"))
+}
+
+async fn respond(mut stream: tokio::net::TcpStream) -> Result<()> {
+    let mut buffer = vec![0; 4096];
+    let length = stream.read(&mut buffer).await?;
+    let request = String::from_utf8_lossy(&buffer[..length]);
+    let mut parts = request.split_whitespace();
+    let (method, path) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    debug!("HTTP {} {}", method, path);
+    let file = resolve(path.split('?').next().unwrap_or(""))
+        .and_then(|(file, content_type)| std::fs::read(file).ok().map(|body| (body, content_type)));
+    let (status, content_type, body) = match (method, file) {
+        ("GET", Some((body, content_type))) => ("200 OK", content_type, body),
+        ("GET", None) => ("404 Not Found", "text/plain", b"Not Found".to_vec()),
+        _ => ("405 Method Not Allowed", "text/plain", b"Method Not Allowed".to_vec()),
+    };
+    let header = format!("HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", status, content_type, body.len());
+    stream.write_all(header.as_bytes()).await?;
+    stream.write_all(&body).await?;
+    Ok(())
+}
+
+/// `serve` command: serve the feeds and the calendar file over HTTP on `feed.listen`
+pub async fn serve() -> Result<()> {
+    let address = config().get_string("feed.listen").unwrap_or(constants::FEED_DEFAULT_LISTEN.to_string());
+    let listener = TcpListener::bind(&address).await?;
+    info!("Serving feeds on http://{}/", address);
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        tokio::spawn(async move {
+            if let Err(e) = respond(stream).await {
+                warn!("Failed to respond to {}: {}", peer, e);
+            }
+        });
+    }
+}
\ No newline at end of file
@@ -0,0 +1,136 @@
+use chrono::{Datelike, NaiveTime};
+use log::{debug, info, warn};
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::{client::Lecture, utils::config};
+
+#[derive(thiserror::Error, Debug)]
+#[allow(clippy::enum_variant_names)]
+pub enum Error {
+    #[error(transparent)]
+    ConfigError(#[from] config::ConfigError),
+    #[error("Invalid regex in `{0}`: {1}")]
+    RegexError(String, regex::Error),
+    #[error("Invalid time `{0}` in `{1}`, expected HH:MM")]
+    TimeParseError(String, String),
+}
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Deserialize, Default)]
+struct TimeWindowConfig {
+    /// 1 = Monday, ..., 7 = Sunday. Empty means every day.
+    #[serde(default)]
+    weekdays: Vec<u32>,
+    start: String,
+    end: String,
+}
+
+#[derive(Deserialize, Default)]
+struct FilterConfig {
+    #[serde(default)]
+    levels: Vec<String>,
+    title: Option<String>,
+    speaker: Option<String>,
+    #[serde(default)]
+    campus: Vec<String>,
+    #[serde(default)]
+    time_windows: Vec<TimeWindowConfig>,
+}
+
+struct TimeWindow {
+    weekdays: Vec<u32>,
+    start: NaiveTime,
+    end: NaiveTime,
+}
+
+/// Rules a lecture must satisfy. Every configured rule has to match, while a lecture only needs
+/// to fall into one of the time windows. Unconfigured rules match everything.
+#[derive(Default)]
+pub struct LectureFilter {
+    levels: Vec<String>,
+    title: Option<Regex>,
+    speaker: Option<Regex>,
+    campus: Vec<String>,
+    time_windows: Vec<TimeWindow>,
+    /// reject lectures whose time cannot be parsed instead of letting them through
+    fail_closed: bool,
+}
+
+impl LectureFilter {
+    /// Load filter rules from config `key`, matching everything if the key is absent
+    pub fn load(key: &str) -> Result<Self> {
+        let filter_config = match config().get::<FilterConfig>(key) {
+            Ok(filter_config) => filter_config,
+            Err(config::ConfigError::NotFound(_)) => return Ok(LectureFilter::default()),
+            Err(e) => return Err(e.into()),
+        };
+        let regex = |field: &str, pattern: Option<String>| pattern
+            .map(|pattern| Regex::new(&pattern).map_err(|e| Error::RegexError(format!("{}.{}", key, field), e)))
+            .transpose();
+        let time = |time: String| NaiveTime::parse_from_str(&time, "%H:%M")
+            .map_err(|_| Error::TimeParseError(time, format!("{}.time_windows", key)));
+        let time_windows = filter_config.time_windows.into_iter()
+            .map(|window| Ok(TimeWindow {
+                weekdays: window.weekdays,
+                start: time(window.start)?,
+                end: time(window.end)?,
+            }))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(LectureFilter {
+            levels: filter_config.levels,
+            title: regex("title", filter_config.title)?,
+            speaker: regex("speaker", filter_config.speaker)?,
+            campus: filter_config.campus,
+            time_windows,
+            fail_closed: false,
+        })
+    }
+
+    /// Reject lectures whose time cannot be checked against the time windows,
+    /// for rules acting on behalf of the user rather than just hiding pushes
+    pub fn fail_closed(self) -> Self {
+        LectureFilter { fail_closed: true, ..self }
+    }
+
+    fn in_time_windows(&self, lecture: &Lecture) -> bool {
+        if self.time_windows.is_empty() {
+            return true;
+        }
+        let Some((start, end)) = lecture.time_range() else {
+            if self.fail_closed {
+                warn!("Failed to parse time of lecture(id = {}), rejected by time windows.", lecture.id);
+                return false;
+            }
+            warn!("Failed to parse time of lecture(id = {}), time windows ignored.", lecture.id);
+            return true;
+        };
+        let weekday = start.weekday().number_from_monday();
+        self.time_windows.iter().any(|window| {
+            (window.weekdays.is_empty() || window.weekdays.contains(&weekday))
+                && start.time() >= window.start
+                && end.time() <= window.end
+        })
+    }
+
+    pub fn matches(&self, lecture: &Lecture) -> bool {
+        let matched = (self.levels.is_empty() || self.levels.contains(&lecture.level))
+            && self.title.as_ref().is_none_or(|regex| regex.is_match(&lecture.title))
+            && self.speaker.as_ref().is_none_or(|regex| regex.is_match(&lecture.speaker))
+            && (self.campus.is_empty() || self.campus.iter().any(|campus| lecture.campus.contains(campus.as_str())))
+            && self.in_time_windows(lecture);
+        if !matched {
+            debug!("Lecture(id = {}) filtered out: {}", lecture.id, lecture.title);
+        }
+        matched
+    }
+
+    pub fn apply(&self, lectures: &[Lecture]) -> Vec<Lecture> {
+        let result: Vec<Lecture> = lectures.iter().filter(|lecture| self.matches(lecture)).cloned().collect();
+        if result.len() != lectures.len() {
+            info!("{} lecture(s) filtered out.", lectures.len() - result.len());
+        }
+        result
+    }
+}
\ No newline at end of file
@@ -0,0 +1,30 @@
+use log::info;
+
+use crate::{client::Client, data::Data, utils::config, Result};
+
+/// Push grades released since the last run. On the first run, existing grades are only recorded.
+pub async fn run(client: &Client, data: &mut Data) -> Result<()> {
+    info!("Getting grades...");
+    let grades = client.get_grades().await?;
+
+    let known: Option<Vec<String>> = data.get("grades").and_then(|known| serde_json::from_str(known).ok());
+    let keys: Vec<String> = grades.iter().map(|grade| grade.key()).collect();
+    data.set("grades", serde_json::to_string(&keys).unwrap());
+    let Some(known) = known else {
+        info!("{} grade(s) recorded on first run, not pushed.", grades.len());
+        return Ok(());
+    };
+
+    let new_grades: Vec<_> = grades.iter().filter(|grade| !known.contains(&grade.key())).collect();
+    info!("{} new grade(s) found.", new_grades.len());
+    if new_grades.is_empty() {
+        return Ok(());
+    }
+    let hide_score = config().get_bool("grade.hide_score").is_ok_and(|v| v);
+    let content = new_grades.iter().map(|grade| {
+        let score = if hide_score { "请在APP中查看" } else { &grade.score };
+        format!("- {}：{}", grade.course_name, score)
+    }).collect::<Vec<String>>().join("\n");
+    client.send_message(&format!("{}门课程成绩已发布", new_grades.len()), &content).await?;
+    Ok(())
+}
\ No newline at end of file
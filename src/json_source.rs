@@ -0,0 +1,120 @@
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{client::Client, data::Data, utils::config, Result};
+
+/// A JSON list endpoint defined in `json_sources`
+#[derive(Deserialize)]
+struct JsonSource {
+    name: String,
+    url: String,
+    /// whether the 1.tongji.edu.cn session is required
+    #[serde(default)]
+    auth: bool,
+    /// dot separated path to the item array, e.g. `data.list`. Empty means the response itself.
+    #[serde(default)]
+    items: String,
+    id: String,
+    title: String,
+    time: Option<String>,
+    link: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonItem {
+    id: String,
+    title: String,
+    time: Option<String>,
+    link: Option<String>,
+}
+
+impl JsonItem {
+    fn format(&self) -> String {
+        let title = match &self.link {
+            Some(link) => format!("[{}]({})", self.title, link),
+            None => self.title.clone(),
+        };
+        match &self.time {
+            Some(time) => format!("{}（{}）", title, time),
+            None => title,
+        }
+    }
+}
+
+/// Look up a dot separated path, array elements can be selected by index
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').filter(|key| !key.is_empty()).try_fold(value, |value, key| match value {
+        Value::Array(array) => array.get(key.parse::<usize>().ok()?),
+        _ => value.get(key),
+    })
+}
+
+fn lookup_string(value: &Value, path: &str) -> Option<String> {
+    match lookup(value, path)? {
+        Value::String(text) => Some(text.clone()),
+        Value::Null => None,
+        value => Some(value.to_string()),
+    }
+}
+
+impl JsonSource {
+    fn parse(&self, json: &Value) -> Vec<JsonItem> {
+        let Some(items) = lookup(json, &self.items).and_then(|items| items.as_array()) else {
+            warn!("No item array found at `{}` in response of source {}", self.items, self.name);
+            return Vec::new();
+        };
+        items.iter().filter_map(|item| {
+            let Some(id) = lookup_string(item, &self.id) else {
+                warn!("Item without id field `{}` in source {}, skipped", self.id, self.name);
+                return None;
+            };
+            Some(JsonItem {
+                title: lookup_string(item, &self.title).unwrap_or_else(|| id.clone()),
+                id,
+                time: self.time.as_ref().and_then(|path| lookup_string(item, path)),
+                link: self.link.as_ref().and_then(|path| lookup_string(item, path)),
+            })
+        }).collect()
+    }
+}
+
+async fn check(client: &Client, data: &mut Data, source: &JsonSource) -> Result<()> {
+    info!("Getting items of source {}...", source.name);
+    let json = client.get_json(&source.url, source.auth).await?;
+    let items = source.parse(&json);
+
+    let key = format!("json_source.{}", source.name);
+    let old_items: Vec<JsonItem> = data.get(&key).and_then(|items| serde_json::from_str(items).ok()).unwrap_or_default();
+    let new_items: Vec<&JsonItem> = items.iter().filter(|item| !old_items.iter().any(|old| old.id == item.id)).collect();
+    // an item already seen is pushed again when its time or link changes, e.g. a rescheduled event
+    let updated_items: Vec<&JsonItem> = items.iter()
+        .filter(|item| old_items.iter().any(|old| old.id == item.id && (old.time != item.time || old.link != item.link)))
+        .collect();
+    info!("{} new and {} updated item(s) found in source {}.", new_items.len(), updated_items.len(), source.name);
+    if !new_items.is_empty() || !updated_items.is_empty() {
+        let content = new_items.iter().map(|item| format!("- {}", item.format()))
+            .chain(updated_items.iter().map(|item| format!("- [更新] {}", item.format())))
+            .collect::<Vec<String>>().join("\n");
+        client.send_message(&format!("{}有{}条更新", source.name, new_items.len() + updated_items.len()), &content).await?;
+    }
+    data.set(&key, serde_json::to_string(&items).unwrap());
+    Ok(())
+}
+
+/// Check every source in `json_sources`. A failing source doesn't stop the others.
+pub async fn run(client: &Client, data: &mut Data) -> Result<()> {
+    let sources: Vec<JsonSource> = match config().get("json_sources") {
+        Ok(sources) => sources,
+        Err(config::ConfigError::NotFound(_)) => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut result = Ok(());
+    for source in &sources {
+        if let Err(e) = check(client, data, source).await {
+            warn!("Failed to check source {}: {}", source.name, e);
+            result = Err(e);
+        }
+    }
+    result
+}
\ No newline at end of file
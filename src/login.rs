@@ -1,9 +1,19 @@
 use base64::Engine;
-use log::debug;
+use log::{debug, info};
 use reqwest::Client;
-use rsa::{pkcs8::DecodePublicKey, RsaPublicKey};
+use rsa::RsaPublicKey;
+
+use crate::constants;
+
+mod sms_provider;
+mod captcha_solver;
+mod diagnosis;
+mod auth_params;
+
+use diagnosis::Diagnosis;
 
 #[derive(thiserror::Error, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
     #[error(transparent)]
     ReqwestError(#[from] reqwest::Error),
@@ -20,8 +30,29 @@ pub enum Error {
     #[error("Failed to read input: {0}")]
     IoError(#[from] std::io::Error),
     #[error("Failed to send SMS code: {0}")]
-    #[allow(dead_code)]
     SMSCodeSendError(String),
+    #[error("No SMS code received in {0} seconds")]
+    SmsCodeTimeout(u64),
+    #[error("Empty SMS code received, login aborted")]
+    EmptySmsCode,
+    #[error("Unknown SMS code provider: {0}, expected stdin, file, http or none")]
+    UnknownSmsProvider(String),
+    #[error("Captcha still required after {0} attempt(s), check `login.captcha` or log in once in a browser to clear it")]
+    CaptchaRequired(u32),
+    #[error("Wrong username or password ({0}), check `login.username` and `login.password`")]
+    WrongPassword(String),
+    #[error("IAM account locked or disabled ({0}), wait for it to be unlocked or reset the password at https://iam.tongji.edu.cn")]
+    AccountLocked(String),
+    #[error("IAM requires SMS verification but `login.sms.provider` is `none`, configure a provider or log in once in a browser")]
+    SmsRequired,
+    #[error("IAM is under maintenance ({0}), try again later")]
+    ServiceMaintenance(String),
+    #[error("Unexpected IAM page at {0} ({1}), the login flow may have changed, rerun with `RUST_LOG=debug` for details")]
+    UnexpectedPage(String, String),
+    #[error("Failed to solve captcha: {0}")]
+    CaptchaSolverError(String),
+    #[error("Failed to decode captcha image: {0}")]
+    ImageError(#[from] image::ImageError),
     #[error("Field not found in response json: {0}")]
     FieldNotFound(String)
 }
@@ -32,9 +63,11 @@ static PRE_LOGIN_URL1: &str = "https://1.tongji.edu.cn/api/ssoservice/system/log
 static LOGIN_URL: &str = "https://iam.tongji.edu.cn/idp/authcenter/ActionAuthChain";
 static LOGIN_URL2: &str = "https://iam.tongji.edu.cn/idp/AuthnEngine";
 static LOGIN_URL3: &str = "https://1.tongji.edu.cn/api/sessionservice/session/login";
-#[allow(dead_code)]
 static SMS_URL: &str = "https://iam.tongji.edu.cn/idp/sendCheckCode.do";
-// static LOGIN_URL: &str = "https://iam.tongji.edu.cn/idp/AuthnEngine";
+static CAPTCHA_URL: &str = "https://iam.tongji.edu.cn/idp/captcha.do";
+/// value the login form posts when no captcha is shown
+static CHECKCODE_PLACEHOLDER: &str = "请输入验证码";
+// built-in values of the auth chain parameters, used when they cannot be scraped from the login page
 static RSA_PUB_KEY: &str = "-----BEGIN PUBLIC KEY-----
 MIGfMA0GCSqGSIb3DQEBAQUAA4GNADCBiQKBgQC9t16RqQWUE/J1IyOfoNHc4r/h
 6RPnXcWTJ4IbhQVUsEqMMm65F0hiytAgozXmVw68yPJywbpblDrx9zl1wdRcdHCo
@@ -44,20 +77,79 @@ EMwsVyJ/lonXaVdGZwIDAQAB
 static SP_AUTH_CHAIN_CODE: &str = "4c1eb8ec14fa4e8ba0f31188dbf88cdd";
 static CURRENT_AUTH: &str = "urn_oasis_names_tc_SAML_2.0_ac_classes_BAMUsernamePassword";
 
-pub async fn encrypt_password(password: &str) -> Result<String> {
+/// Run a blocking read on a thread of its own. Unlike `spawn_blocking`, an abandoned read (e.g. after a timeout)
+/// does not keep the runtime from shutting down.
+async fn read_detached(read: impl FnOnce() -> std::io::Result<String> + Send + 'static) -> Result<String> {
+    let (sender, receiver) = tokio::sync::oneshot::channel();
+    std::thread::spawn(move || sender.send(read()));
+    receiver.await.map_err(std::io::Error::other)?.map_err(Error::IoError)
+}
+
+/// Prompt and read a line from the terminal
+async fn read_line(prompt: &str) -> Result<String> {
+    println!("{}", prompt);
+    read_detached(|| {
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line).map(|_| line)
+    }).await
+}
+
+/// Wait until something is written to `path`, which can be a regular file or a FIFO.
+/// A regular file is removed before and after reading so that stale input is never reused.
+async fn wait_for_file(path: &str) -> Result<String> {
+    if std::fs::metadata(path).is_ok_and(|metadata| metadata.is_file()) {
+        std::fs::remove_file(path)?;
+    }
+    loop {
+        // reading a FIFO blocks until something is written
+        let owned_path = path.to_string();
+        match read_detached(move || std::fs::read_to_string(owned_path)).await {
+            Ok(content) if !content.trim().is_empty() => {
+                if std::fs::metadata(path).is_ok_and(|metadata| metadata.is_file()) {
+                    std::fs::remove_file(path)?;
+                }
+                return Ok(content);
+            },
+            _ => tokio::time::sleep(std::time::Duration::from_secs(1)).await,
+        }
+    }
+}
+
+/// Turn a failed IAM response into the matching error, `captcha_attempts` being the captchas solved so far
+fn failure(url: &reqwest::Url, status: reqwest::StatusCode, text: &str, captcha_attempts: u32) -> Error {
+    match diagnosis::diagnose(status, text) {
+        Diagnosis::WrongPassword(message) => Error::WrongPassword(message),
+        Diagnosis::AccountLocked(message) => Error::AccountLocked(message),
+        Diagnosis::Maintenance(message) => Error::ServiceMaintenance(message),
+        Diagnosis::CaptchaRequired => Error::CaptchaRequired(captcha_attempts),
+        Diagnosis::SmsRequired => Error::SmsRequired,
+        Diagnosis::Unknown => Error::UnexpectedPage(url.to_string(), diagnosis::message(text).unwrap_or(status.to_string())),
+    }
+}
+
+fn has_token(url: &reqwest::Url) -> bool {
+    url.query_pairs().any(|(key, _)| key == "token")
+}
+
+pub async fn encrypt_password(rsa_pub_key: &RsaPublicKey, password: &str) -> Result<String> {
     let mut rng = rand::rngs::OsRng;
-    let rsa_pub_key = RsaPublicKey::from_public_key_pem(&RSA_PUB_KEY)?;
-    let encrypted_password = rsa_pub_key.encrypt(&mut rng, rsa::Pkcs1v15Encrypt, &password.as_bytes())?;
+    let encrypted_password = rsa_pub_key.encrypt(&mut rng, rsa::Pkcs1v15Encrypt, password.as_bytes())?;
     let result = base64::engine::general_purpose::STANDARD.encode(&encrypted_password);
     Ok(result)
 }
 
 pub async fn login(client: &Client, username: &str, password: &str) -> Result<String> {
-    debug!("trying to login with username: {}, password: {}", username, password);
+    debug!("trying to login with username: {}", username);
     // make request to get redirect url with param entity_id and authn_lc_key
     let response = client.get(PRE_LOGIN_URL1).send().await?;
-    debug!("login 1st request: {:?}", response);
-    let params = response.url().query().ok_or(Error::NoUrlParamsError)?;
+    debug!("login 1st request: {} {}", response.status(), response.url());
+    let login_page_url = response.url().clone();
+    if has_token(&login_page_url) {
+        // IAM cookies restored from the last run are still valid, single sign-on skips the password
+        info!("Logged in silently with IAM single sign-on.");
+        return create_session(client, &login_page_url).await;
+    }
+    let params = login_page_url.query().ok_or(Error::NoUrlParamsError)?;
     let mut entity_id = None;
     let mut authn_lc_key = None;
     params.split("&")
@@ -75,73 +167,126 @@ pub async fn login(client: &Client, username: &str, password: &str) -> Result<St
         });
     let entity_id = entity_id.ok_or(Error::UrlParamsNotFoundError("entityId".to_string()))?;
     let authn_lc_key = authn_lc_key.ok_or(Error::UrlParamsNotFoundError("authnLcKey".to_string()))?;
-    let encrypted_password = encrypt_password(password).await?;
-    debug!("encrypted password: {:?}", encrypted_password);
-    let response = client.post(LOGIN_URL)
-        .header("Referer", format!("https://iam.tongji.edu.cn/idp/authcenter/ActionAuthChain?entityId={}&authnLcKey={}", entity_id, authn_lc_key))
-        .query(&[
-            ("authnLcKey", authn_lc_key),
-        ]).form(&[
-            ("j_username", username),
-            ("j_password", &encrypted_password),
-            ("j_checkcode", "请输入验证码"),
-            ("op", "login"),
-            ("spAuthChainCode", SP_AUTH_CHAIN_CODE),
-            ("authnLcKey", authn_lc_key),
-        ]).send().await?;
-    debug!("login 2nd request: {:?}", response);
+    let auth_params = auth_params::scrape(&response.text().await?)?;
+    let encrypted_password = encrypt_password(&auth_params.rsa_pub_key, password).await?;
+    let mut checkcode = CHECKCODE_PLACEHOLDER.to_string();
+    let mut attempts = 0;
+    loop {
+        let response = client.post(LOGIN_URL)
+            .header("Referer", format!("https://iam.tongji.edu.cn/idp/authcenter/ActionAuthChain?entityId={}&authnLcKey={}", entity_id, authn_lc_key))
+            .query(&[
+                ("authnLcKey", authn_lc_key),
+            ]).form(&[
+                ("j_username", username),
+                ("j_password", &encrypted_password),
+                ("j_checkcode", &checkcode),
+                ("op", "login"),
+                ("spAuthChainCode", &auth_params.sp_auth_chain_code),
+                ("authnLcKey", authn_lc_key),
+            ]).send().await?;
+        debug!("login 2nd request: {} {}", response.status(), response.url());
+        let (url, status) = (response.url().clone(), response.status());
+        let text = response.text().await?;
+        match diagnosis::diagnose(status, &text) {
+            Diagnosis::CaptchaRequired => (),
+            Diagnosis::Unknown | Diagnosis::SmsRequired => break,
+            _ => return Err(failure(&url, status, &text, attempts)),
+        }
+        if attempts >= constants::CAPTCHA_MAX_ATTEMPTS {
+            return Err(Error::CaptchaRequired(attempts));
+        }
+        attempts += 1;
+        info!("Captcha required, solving (attempt {})...", attempts);
+        let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+        let image = client.get(CAPTCHA_URL)
+            .query(&[("t", timestamp)])
+            .send().await?
+            .error_for_status()?
+            .bytes().await?;
+        checkcode = captcha_solver::solve(&image).await?;
+    }
     // make request to login and get redirect url
     let response = client.post(LOGIN_URL2)
         .header("Referer", format!("https://iam.tongji.edu.cn/idp/authcenter/ActionAuthChain?entityId={}&authnLcKey={}", entity_id, authn_lc_key))
         .query(&[
             ("entityId", entity_id),
-            ("currentAuth", CURRENT_AUTH),
+            ("currentAuth", &auth_params.current_auth),
             ("authnLcKey", authn_lc_key),
         ]).form(&[
             ("j_username", username),
             ("j_password", &encrypted_password),
-            ("j_checkcode", "请输入验证码"),
+            ("j_checkcode", &checkcode),
             ("op", "login"),
-            ("spAuthChainCode", SP_AUTH_CHAIN_CODE),
+            ("spAuthChainCode", &auth_params.sp_auth_chain_code),
             ("authnLcKey", authn_lc_key),
         ]).send().await?;
-    debug!("login 3rd request: {:?}", response);
-    debug!("login 3rd request header: {:?}", response.headers());
-    // let text = response.text().await?;
-    // if text.contains("增强认证") {
-    //     debug!("Need to send sms");
-    //     let response = client.post(SMS_URL)
-    //         .header("Referer", format!("https://iam.tongji.edu.cn/idp/authcenter/ActionAuthChain?entityId={}&authnLcKey={}", entity_id, authn_lc_key))
-    //         .form(&[
-    //             ("j_username", username),
-    //             ("type", "sms")
-    //         ]).send().await?;
-    //     if response.status().is_success() {
-    //         info!("已发送验证码");
-    //     } else {
-    //         info!("发送验证码失败");
-    //         return Err(Error::SMSCodeSendError(response.text().await?));
-    //     }
-    //     let mut sms_code = String::default();
-    //     std::io::stdin().read_line(&mut sms_code)?;
-
-    //     let response = client.post(LOGIN_URL)
-    //         .header("Referer", format!("https://iam.tongji.edu.cn/idp/authcenter/ActionAuthChain?entityId={}&authnLcKey={}", entity_id, authn_lc_key))
-    //         .query(&[("authnLcKey", authn_lc_key)])
-    //         .form(&[
-    //             ("j_username", username),
-    //             ("type", "sms"),
-    //             ("sms_checkcode", &sms_code),
-    //             ("popViewException", "Pop2"),
-    //             ("op", "login"),
-    //             ("spAuthChainCode", SP_AUTH_CHAIN_CODE),
-    //             ("j_checkcode", "请输入验证码"),
-    //         ]).send().await;
-    //     debug!("sms login request: {:?}", response);
-    // }
+    debug!("login 3rd request: {} {}", response.status(), response.url());
+    let (mut url, status) = (response.url().clone(), response.status());
+    let text = response.text().await?;
+    if !has_token(&url) {
+        if !matches!(diagnosis::diagnose(status, &text), Diagnosis::SmsRequired) {
+            return Err(failure(&url, status, &text, attempts));
+        }
+        info!("Enhanced authentication required, sending SMS code...");
+        let referer = format!("https://iam.tongji.edu.cn/idp/authcenter/ActionAuthChain?entityId={}&authnLcKey={}", entity_id, authn_lc_key);
+        let response = client.post(SMS_URL)
+            .header("Referer", &referer)
+            .form(&[
+                ("j_username", username),
+                ("type", "sms")
+            ]).send().await?;
+        if !response.status().is_success() {
+            return Err(Error::SMSCodeSendError(response.text().await?));
+        }
+        info!("SMS code sent.");
+        let sms_code = sms_provider::get_sms_code().await?;
+
+        let response = client.post(LOGIN_URL)
+            .header("Referer", &referer)
+            .query(&[("authnLcKey", authn_lc_key)])
+            .form(&[
+                ("j_username", username),
+                ("type", "sms"),
+                ("sms_checkcode", &sms_code),
+                ("popViewException", "Pop2"),
+                ("op", "login"),
+                ("spAuthChainCode", &auth_params.sp_auth_chain_code),
+                ("j_checkcode", &checkcode),
+            ]).send().await?;
+        debug!("sms login request: {} {}", response.status(), response.url());
+        url = response.url().clone();
+        if !has_token(&url) {
+            // continue the auth chain like after the password step
+            let response = client.post(LOGIN_URL2)
+                .header("Referer", &referer)
+                .query(&[
+                    ("entityId", entity_id),
+                    ("currentAuth", &auth_params.current_auth),
+                    ("authnLcKey", authn_lc_key),
+                ]).form(&[
+                    ("j_username", username),
+                    ("type", "sms"),
+                    ("op", "login"),
+                    ("spAuthChainCode", &auth_params.sp_auth_chain_code),
+                    ("authnLcKey", authn_lc_key),
+                ]).send().await?;
+            debug!("sms login 2nd request: {} {}", response.status(), response.url());
+            url = response.url().clone();
+            let status = response.status();
+            let text = response.text().await?;
+            if !has_token(&url) {
+                return Err(failure(&url, status, &text, attempts));
+            }
+        }
+    }
+    create_session(client, &url).await
+}
+
+/// Exchange the token IAM redirected to for a 1.tongji.edu.cn session id
+async fn create_session(client: &Client, url: &reqwest::Url) -> Result<String> {
     // get token, uid, ts from params
     let (mut token, mut uid, mut ts) = (None, None, None);
-    response.url().query_pairs().for_each(|(key, value)| {
+    url.query_pairs().for_each(|(key, value)| {
         let value = (*value).to_string();
         match &*key {
             "token" => token = Some(value),
@@ -153,7 +298,6 @@ pub async fn login(client: &Client, username: &str, password: &str) -> Result<St
     let token = token.ok_or(Error::UrlParamsNotFoundError("token".to_string()))?;
     let uid = uid.ok_or(Error::UrlParamsNotFoundError("uid".to_string()))?;
     let ts = ts.ok_or(Error::UrlParamsNotFoundError("ts".to_string()))?;
-    debug!("token: {}, uid: {}, ts: {}", token, uid, ts);
 
     let response = client.post(LOGIN_URL3)
         .header("Referer", format!("https://1.tongji.edu.cn/ssologin?token={}&uid={}&ts={}", token, uid, ts))
@@ -165,7 +309,7 @@ pub async fn login(client: &Client, username: &str, password: &str) -> Result<St
     let session_id = response.get("data")
         .and_then(|map| map.get("sessionid"))
         .and_then(|session_id| session_id.as_str())
-        .and_then(|s| Some(s.to_string()));
-    debug!("login result: session_id = {:?}", session_id);
+        .map(|s| s.to_string());
+    debug!("login result: session id {}", if session_id.is_some() { "received" } else { "missing" });
     session_id.ok_or(Error::FieldNotFound("data.sessionid".to_string()))
 }
\ No newline at end of file
@@ -0,0 +1,121 @@
+use base64::Engine;
+use log::warn;
+use regex::Regex;
+use rsa::{pkcs8::DecodePublicKey, RsaPublicKey};
+
+use super::{Result, CURRENT_AUTH, RSA_PUB_KEY, SP_AUTH_CHAIN_CODE};
+
+/// Parameters of the IAM auth chain that are embedded in the login page
+pub struct AuthParams {
+    pub rsa_pub_key: RsaPublicKey,
+    pub sp_auth_chain_code: String,
+    pub current_auth: String,
+}
+
+fn capture(text: &str, pattern: &str) -> Option<String> {
+    Regex::new(pattern).unwrap().captures(text).map(|captures| captures[1].to_string())
+}
+
+/// Value of the field `name` in the login page, matching `value`: either a hidden input like
+/// `<input name="..." value="...">` in any attribute order, or a JS assignment like `name = "..."` or `"name": "..."`
+fn scrape_field(text: &str, name: &str, value: &str) -> Option<String> {
+    let name = regex::escape(name);
+    let input = Regex::new(&format!(r#"<input\b[^>]*\b(?:name|id)\s*=\s*["']{}["'][^>]*>"#, name)).unwrap();
+    let value_attribute = format!(r#"\bvalue\s*=\s*["']({})["']"#, value);
+    let from_input = input.find_iter(text).find_map(|tag| capture(tag.as_str(), &value_attribute));
+    from_input.or_else(|| capture(text, &format!(r#"\b{}["']?\s*[:=]\s*["']({})["']"#, name, value)))
+}
+
+fn scrape_pub_key(text: &str) -> Option<RsaPublicKey> {
+    // the key is embedded as the base64 of a DER SubjectPublicKeyInfo, 1024 or 2048 bits
+    let key = capture(text, r"(MIGfMA0GCSqGSIb3DQEBAQUAA4GNADCBiQKBgQ[A-Za-z0-9+/]+=*|MIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8AMIIBCgKCAQ[A-Za-z0-9+/]+=*)")?;
+    let der = base64::engine::general_purpose::STANDARD.decode(key).ok()?;
+    RsaPublicKey::from_public_key_der(&der).ok()
+}
+
+/// Pick the scraped value, falling back to the built-in one if scraping failed
+fn choose<T: PartialEq>(name: &str, scraped: Option<T>, builtin: T) -> T {
+    match scraped {
+        Some(scraped) if scraped != builtin => {
+            warn!("IAM {} differs from the built-in value, using the one from the login page.", name);
+            scraped
+        },
+        Some(scraped) => scraped,
+        None => {
+            warn!("IAM {} not found in the login page, using the built-in value. The login page may have changed.", name);
+            builtin
+        },
+    }
+}
+
+/// Scrape the auth chain parameters from the login page
+pub fn scrape(text: &str) -> Result<AuthParams> {
+    Ok(AuthParams {
+        rsa_pub_key: choose("RSA public key", scrape_pub_key(text), RsaPublicKey::from_public_key_pem(RSA_PUB_KEY)?),
+        sp_auth_chain_code: choose("spAuthChainCode",
+            scrape_field(text, "spAuthChainCode", "[0-9a-fA-F]{32}"),
+            SP_AUTH_CHAIN_CODE.to_string()),
+        current_auth: choose("currentAuth",
+            scrape_field(text, "currentAuth", "urn_[A-Za-z0-9_.]+"),
+            CURRENT_AUTH.to_string()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static CODE: &str = "0123456789abcdef0123456789ABCDEF";
+
+    #[test]
+    fn hidden_inputs() {
+        let page = format!(r#"<form id="loginForm">
+            <input type="hidden" name="spAuthChainCode" id="spAuthChainCode" value="{}"/>
+            <input type="hidden" id="currentAuth" value="urn_oasis_names_tc_SAML_2.0_ac_classes_Test">
+        </form>"#, CODE);
+        assert_eq!(scrape_field(&page, "spAuthChainCode", "[0-9a-fA-F]{32}").as_deref(), Some(CODE));
+        assert_eq!(scrape_field(&page, "currentAuth", "urn_[A-Za-z0-9_.]+").as_deref(), Some("urn_oasis_names_tc_SAML_2.0_ac_classes_Test"));
+    }
+
+    #[test]
+    fn value_before_name() {
+        let page = format!(r#"<input value='{}' type='hidden' name='spAuthChainCode'>"#, CODE);
+        assert_eq!(scrape_field(&page, "spAuthChainCode", "[0-9a-fA-F]{32}").as_deref(), Some(CODE));
+    }
+
+    #[test]
+    fn js_assignments() {
+        let page = format!(r#"<script>
+            var spAuthChainCode = "{}";
+            var params = {{ "currentAuth": "urn_oasis_names_tc_SAML_2.0_ac_classes_Test", other: 1 }};
+        </script>"#, CODE);
+        assert_eq!(scrape_field(&page, "spAuthChainCode", "[0-9a-fA-F]{32}").as_deref(), Some(CODE));
+        assert_eq!(scrape_field(&page, "currentAuth", "urn_[A-Za-z0-9_.]+").as_deref(), Some("urn_oasis_names_tc_SAML_2.0_ac_classes_Test"));
+    }
+
+    #[test]
+    fn other_fields_ignored() {
+        let page = format!(r#"<input name="authnLcKey" value="{}"><input name="spAuthChainCode" value="">"#, CODE);
+        assert_eq!(scrape_field(&page, "spAuthChainCode", "[0-9a-fA-F]{32}"), None);
+    }
+
+    #[test]
+    fn scrape_falls_back_to_builtin() {
+        let params = scrape("<html></html>").unwrap();
+        assert_eq!(params.sp_auth_chain_code, SP_AUTH_CHAIN_CODE);
+        assert_eq!(params.current_auth, CURRENT_AUTH);
+        assert_eq!(params.rsa_pub_key, RsaPublicKey::from_public_key_pem(RSA_PUB_KEY).unwrap());
+    }
+
+    #[test]
+    fn scrape_page() {
+        let key = RSA_PUB_KEY.lines().filter(|line| !line.starts_with("-----")).collect::<String>();
+        let page = format!(r#"<script>var publicKey = "{}";</script>
+            <input type="hidden" name="spAuthChainCode" value="{}">
+            <input type="hidden" name="currentAuth" value="urn_test">"#, key, CODE);
+        let params = scrape(&page).unwrap();
+        assert_eq!(params.sp_auth_chain_code, CODE);
+        assert_eq!(params.current_auth, "urn_test");
+        assert_eq!(params.rsa_pub_key, RsaPublicKey::from_public_key_pem(RSA_PUB_KEY).unwrap());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,106 @@
+use std::{io::IsTerminal, process::Stdio, time::Duration};
+
+use image::GenericImageView;
+use log::info;
+use tokio::io::AsyncWriteExt;
+
+use crate::{constants, utils::config};
+
+use super::{read_line, wait_for_file, Error, Result};
+
+/// Turns a captcha image into its text
+pub trait CaptchaSolver {
+    async fn solve(image: &[u8]) -> Result<String>;
+}
+
+/// Draw the captcha in the terminal and read the answer from stdin
+pub struct TerminalSolver;
+
+/// Save the captcha to `login.captcha.path` and wait for the answer in `login.captcha.answer_path`
+pub struct FileSolver;
+
+/// Pipe the captcha into `login.captcha.command` and use its stdout as the answer
+pub struct CommandSolver;
+
+impl TerminalSolver {
+    /// Render with upper half blocks, each character cell showing two pixel rows in true color
+    fn render(image: &[u8]) -> Result<String> {
+        let image = image::load_from_memory(image)?;
+        let (width, height) = image.dimensions();
+        let scale = (width as f32 / constants::CAPTCHA_TERMINAL_WIDTH as f32).max(1.0);
+        let image = image.resize_exact((width as f32 / scale) as u32, (height as f32 / scale) as u32, image::imageops::FilterType::Triangle).to_rgb8();
+        let mut output = String::new();
+        for y in (0..image.height()).step_by(2) {
+            for x in 0..image.width() {
+                let top = image.get_pixel(x, y);
+                let bottom = if y + 1 < image.height() { image.get_pixel(x, y + 1) } else { top };
+                output += &format!("\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m▀", top[0], top[1], top[2], bottom[0], bottom[1], bottom[2]);
+            }
+            output += "\x1b[0m\n";
+        }
+        Ok(output)
+    }
+}
+
+impl CaptchaSolver for TerminalSolver {
+    async fn solve(image: &[u8]) -> Result<String> {
+        print!("{}", TerminalSolver::render(image)?);
+        read_line("请输入上图中的验证码：").await
+    }
+}
+
+impl CaptchaSolver for FileSolver {
+    async fn solve(image: &[u8]) -> Result<String> {
+        let path = config().get_string("login.captcha.path").unwrap_or(constants::CAPTCHA_DEFAULT_PATH.to_string());
+        let answer_path = config().get_string("login.captcha.answer_path").unwrap_or(constants::CAPTCHA_DEFAULT_ANSWER_PATH.to_string());
+        std::fs::write(&path, image)?;
+        info!("Captcha saved to {}, waiting for the answer in {}...", path, answer_path);
+        wait_for_file(&answer_path).await
+    }
+}
+
+impl CaptchaSolver for CommandSolver {
+    async fn solve(image: &[u8]) -> Result<String> {
+        let command = config().get_string("login.captcha.command").map_err(|_| Error::CaptchaSolverError("`login.captcha.command` not configured".to_string()))?;
+        info!("Solving captcha with command: {}", command);
+        let (shell, flag) = if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+        let mut child = tokio::process::Command::new(shell)
+            .args([flag, &command])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let mut stdin = child.stdin.take().unwrap();
+        stdin.write_all(image).await?;
+        drop(stdin);
+        let output = child.wait_with_output().await?;
+        if !output.status.success() {
+            return Err(Error::CaptchaSolverError(format!("command exited with {}", output.status)));
+        }
+        Ok(String::from_utf8(output.stdout)?)
+    }
+}
+
+/// Solve a captcha with the solver configured in `login.captcha.solver`, giving up after `login.captcha.timeout` seconds.
+/// Without a solver configured, the captcha is shown on the terminal if there is one.
+pub async fn solve(image: &[u8]) -> Result<String> {
+    let default_solver = if std::io::stdin().is_terminal() { "terminal" } else { "none" };
+    let solver = config().get_string("login.captcha.solver").unwrap_or(default_solver.to_string());
+    let timeout = config().get_int("login.captcha.timeout").unwrap_or(constants::CAPTCHA_DEFAULT_TIMEOUT_SECS).max(1) as u64;
+    let answer = async {
+        match solver.to_lowercase().as_str() {
+            "terminal" => TerminalSolver::solve(image).await,
+            "file" => FileSolver::solve(image).await,
+            "command" => CommandSolver::solve(image).await,
+            // unattended runs fail fast instead of posting the password with empty answers
+            "none" => Err(Error::CaptchaSolverError("`login.captcha.solver` is `none`, configure a solver or log in once in a browser".to_string())),
+            _ => Err(Error::CaptchaSolverError(format!("unknown solver {}, expected terminal, file, command or none", solver))),
+        }
+    };
+    let answer = tokio::time::timeout(Duration::from_secs(timeout), answer).await
+        .map_err(|_| Error::CaptchaSolverError(format!("no answer in {} seconds", timeout)))??;
+    let answer = answer.trim();
+    if answer.is_empty() {
+        return Err(Error::CaptchaSolverError("empty answer".to_string()));
+    }
+    Ok(answer.to_string())
+}
\ No newline at end of file
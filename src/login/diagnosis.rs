@@ -0,0 +1,103 @@
+use reqwest::StatusCode;
+use scraper::{Html, Selector};
+
+/// What an IAM response says about the login attempt
+#[derive(Debug, PartialEq)]
+pub enum Diagnosis {
+    WrongPassword(String),
+    AccountLocked(String),
+    CaptchaRequired,
+    SmsRequired,
+    Maintenance(String),
+    /// nothing recognized, the flow may go on
+    Unknown,
+}
+
+// only phrases stating the account is locked, since warnings like "密码错误，连续错误5次将锁定账号" mention locking too
+static LOCKED_KEYWORDS: [&str; 7] = ["已锁定", "已被锁定", "锁定中", "已冻结", "已被冻结", "已停用", "已被停用"];
+static WRONG_PASSWORD_KEYWORDS: [&str; 5] = ["密码错误", "用户名或密码", "账号或密码", "密码不正确", "用户不存在"];
+static CAPTCHA_KEYWORDS: [&str; 3] = ["验证码错误", "验证码不能为空", "请输入正确的验证码"];
+static SMS_KEYWORDS: [&str; 2] = ["增强认证", "短信验证"];
+static MAINTENANCE_KEYWORDS: [&str; 3] = ["系统维护", "维护中", "暂停服务"];
+
+/// The message IAM shows to the user, from the JSON fields or the error element of the page
+pub fn message(text: &str) -> Option<String> {
+    if let Ok(json) = serde_json::from_str::<serde_json::Value>(text) {
+        return ["loginFailedMsg", "errorMsg", "msg", "message"].iter()
+            .find_map(|key| json.get(key).and_then(|value| value.as_str()))
+            .filter(|message| !message.trim().is_empty())
+            .map(|message| message.trim().to_string());
+    }
+    let html = Html::parse_document(text);
+    let selector = Selector::parse("#errorMsg, .error-msg, .errorMsg, .login-error, title").unwrap();
+    html.select(&selector)
+        .map(|element| element.text().collect::<String>().trim().to_string())
+        .find(|message| !message.is_empty())
+}
+
+/// Whether a JSON response asks for a captcha through its `checkCode` flag
+fn captcha_flagged(text: &str) -> bool {
+    serde_json::from_str::<serde_json::Value>(text).ok()
+        .and_then(|json| json.get("checkCode").cloned())
+        .is_some_and(|flag| flag == serde_json::Value::Bool(true) || flag.as_str() == Some("true"))
+}
+
+/// Classify an IAM response. Account states are checked before the password since the lock message
+/// usually mentions failed passwords as well.
+/// Keywords are looked up in the message only, since the login page ships scripts and hints mentioning
+/// most of them; the whole body is searched only when no message could be extracted.
+pub fn diagnose(status: StatusCode, text: &str) -> Diagnosis {
+    let message = message(text);
+    let haystack = message.as_deref().unwrap_or(text);
+    let contains = |keywords: &[&str]| keywords.iter().any(|keyword| haystack.contains(keyword));
+    let message = message.clone().unwrap_or_default();
+    if status.is_server_error() || contains(&MAINTENANCE_KEYWORDS) {
+        Diagnosis::Maintenance(if message.is_empty() { status.to_string() } else { message })
+    } else if contains(&LOCKED_KEYWORDS) {
+        Diagnosis::AccountLocked(message)
+    } else if contains(&CAPTCHA_KEYWORDS) || captcha_flagged(text) {
+        Diagnosis::CaptchaRequired
+    } else if contains(&WRONG_PASSWORD_KEYWORDS) {
+        Diagnosis::WrongPassword(message)
+    } else if contains(&SMS_KEYWORDS) {
+        Diagnosis::SmsRequired
+    } else {
+        Diagnosis::Unknown
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diagnose_iam_messages() {
+        let cases = [
+            (StatusCode::OK, r#"{"loginFailedMsg":"用户名或密码错误"}"#, Diagnosis::WrongPassword("用户名或密码错误".to_string())),
+            (StatusCode::OK, r#"{"loginFailedMsg":"密码错误，连续错误5次将锁定账号"}"#, Diagnosis::WrongPassword("密码错误，连续错误5次将锁定账号".to_string())),
+            (StatusCode::OK, r#"{"loginFailedMsg":"用户不存在"}"#, Diagnosis::WrongPassword("用户不存在".to_string())),
+            (StatusCode::OK, r#"{"loginFailedMsg":"密码错误次数过多，账号已锁定"}"#, Diagnosis::AccountLocked("密码错误次数过多，账号已锁定".to_string())),
+            (StatusCode::OK, r#"{"errorMsg":"账号已被停用"}"#, Diagnosis::AccountLocked("账号已被停用".to_string())),
+            (StatusCode::OK, r#"{"loginFailedMsg":"验证码错误"}"#, Diagnosis::CaptchaRequired),
+            (StatusCode::OK, r#"{"checkCode":"true"}"#, Diagnosis::CaptchaRequired),
+            (StatusCode::OK, "<html><div id=\"errorMsg\">请进行增强认证</div></html>", Diagnosis::SmsRequired),
+            (StatusCode::OK, "<html><title>系统维护中</title></html>", Diagnosis::Maintenance("系统维护中".to_string())),
+            (StatusCode::BAD_GATEWAY, "", Diagnosis::Maintenance(StatusCode::BAD_GATEWAY.to_string())),
+            (StatusCode::OK, "<html><title>统一身份认证</title></html>", Diagnosis::Unknown),
+            (StatusCode::OK, "<html><title>统一身份认证</title><script>var tip = \"密码错误\";</script></html>", Diagnosis::Unknown),
+            (StatusCode::OK, r#"{"msg":"ok","tip":"账号已锁定"}"#, Diagnosis::Unknown),
+            (StatusCode::OK, r#"{"checkCode":true}"#, Diagnosis::CaptchaRequired),
+            (StatusCode::OK, r#"{"result":"账号或密码不正确"}"#, Diagnosis::WrongPassword(String::new())),
+        ];
+        for (status, text, expected) in cases {
+            assert_eq!(diagnose(status, text), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn message_from_json_and_html() {
+        assert_eq!(message(r#"{"msg":"  请稍后再试 "}"#), Some("请稍后再试".to_string()));
+        assert_eq!(message(r#"{"msg":""}"#), None);
+        assert_eq!(message("<html><span class=\"error-msg\">密码错误</span></html>"), Some("密码错误".to_string()));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,86 @@
+use std::{io::IsTerminal, time::Duration};
+
+use log::{info, warn};
+use tokio::{io::{AsyncReadExt, AsyncWriteExt}, net::TcpListener};
+
+use crate::{constants, utils::config};
+
+use super::{read_line, wait_for_file, Error, Result};
+
+/// Source of the SMS code required by IAM enhanced authentication
+pub trait SmsCodeProvider {
+    async fn get_code() -> Result<String>;
+}
+
+/// Read the code from the terminal
+pub struct StdinProvider;
+
+/// Wait for the code to be written to `login.sms.path`, which can be a regular file or a FIFO
+pub struct FileProvider;
+
+/// Wait for a request like `GET /?code=123456` on `login.sms.listen`
+pub struct HttpProvider;
+
+impl SmsCodeProvider for StdinProvider {
+    async fn get_code() -> Result<String> {
+        read_line("请输入短信验证码：").await
+    }
+}
+
+impl SmsCodeProvider for FileProvider {
+    async fn get_code() -> Result<String> {
+        let path = config().get_string("login.sms.path").unwrap_or(constants::SMS_CODE_DEFAULT_PATH.to_string());
+        info!("Waiting for SMS code in {}...", path);
+        wait_for_file(&path).await
+    }
+}
+
+impl SmsCodeProvider for HttpProvider {
+    async fn get_code() -> Result<String> {
+        let address = config().get_string("login.sms.listen").unwrap_or(constants::SMS_CODE_DEFAULT_LISTEN.to_string());
+        let listener = TcpListener::bind(&address).await?;
+        info!("Waiting for SMS code on http://{}/?code=...", address);
+        loop {
+            let (mut stream, _) = listener.accept().await?;
+            let mut buffer = vec![0; 4096];
+            let length = stream.read(&mut buffer).await?;
+            let request = String::from_utf8_lossy(&buffer[..length]);
+            let path = request.split_whitespace().nth(1).unwrap_or("/");
+            let code = reqwest::Url::parse(&format!("http://localhost{}", path)).ok()
+                .and_then(|url| url.query_pairs().find(|(key, _)| key == "code").map(|(_, code)| code.to_string()));
+            let (status, body) = if code.is_some() { ("200 OK", "OK") } else { ("400 Bad Request", "Missing parameter: code") };
+            let response = format!("HTTP/1.1 {}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", status, body.len(), body);
+            if let Err(e) = stream.write_all(response.as_bytes()).await {
+                warn!("Failed to respond to SMS code request: {}", e);
+            }
+            if let Some(code) = code {
+                return Ok(code);
+            }
+        }
+    }
+}
+
+/// Get the SMS code from the provider configured in `login.sms.provider`, giving up after `login.sms.timeout` seconds.
+/// Without a provider configured, the code is asked on the terminal if there is one.
+pub async fn get_sms_code() -> Result<String> {
+    let default_provider = if std::io::stdin().is_terminal() { "stdin" } else { "none" };
+    let provider = config().get_string("login.sms.provider").unwrap_or(default_provider.to_string());
+    let timeout = config().get_int("login.sms.timeout").unwrap_or(constants::SMS_CODE_DEFAULT_TIMEOUT_SECS).max(1) as u64;
+    let code = async {
+        match provider.to_lowercase().as_str() {
+            "stdin" => StdinProvider::get_code().await,
+            "file" => FileProvider::get_code().await,
+            "http" => HttpProvider::get_code().await,
+            // unattended runs fail fast instead of waiting for a code nobody will enter
+            "none" => Err(Error::SmsRequired),
+            _ => Err(Error::UnknownSmsProvider(provider.clone())),
+        }
+    };
+    let code = tokio::time::timeout(Duration::from_secs(timeout), code).await
+        .map_err(|_| Error::SmsCodeTimeout(timeout))??;
+    let code = code.trim();
+    if code.is_empty() {
+        return Err(Error::EmptySmsCode);
+    }
+    Ok(code.to_string())
+}
\ No newline at end of file
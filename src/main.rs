@@ -7,25 +7,65 @@ mod data;
 mod constants;
 mod client;
 mod login;
+mod calendar;
+mod filter;
+mod auto_apply;
+mod watch;
+mod progress;
+mod grades;
+mod exams;
+mod timetable;
+mod messages;
+mod selection;
+mod json_source;
+mod rss;
+mod feed;
+mod session;
+mod redact;
+mod credentials;
+mod encryption;
 
 #[derive(thiserror::Error, Debug)]
+#[allow(clippy::enum_variant_names)]
 enum Error {
     #[error(transparent)]
     ConfigError(#[from] config::ConfigError),
     #[error(transparent)]
     ClientError(#[from] client::Error),
+    #[error(transparent)]
+    CalendarError(#[from] calendar::Error),
+    #[error(transparent)]
+    FilterError(#[from] filter::Error),
+    #[error(transparent)]
+    FeedError(#[from] feed::Error),
+    #[error(transparent)]
+    CredentialError(#[from] credentials::Error),
 }
 
 type Result<T> = std::result::Result<T, Error>;
 
 #[tokio::main]
 async fn main() {
-    pretty_env_logger::init_timed();
+    redact::init_logger();
     info!("Starting...");
     let client = client::Client::new();
-    if let Err(e) = work(&client).await {
+    let result = match std::env::args().nth(1).as_deref() {
+        None | Some("run") => work(&client).await,
+        Some("watch") => watch::run(&client).await,
+        Some("status") => progress::status(&client).await,
+        Some("serve") => feed::serve().await.map_err(Error::from),
+        Some("login") => session::login(&client).await,
+        Some(command) => {
+            error!("Unknown command: {}. Available commands: run, watch, status, serve, login", command);
+            return;
+        }
+    };
+    if let Err(e) = result {
         error!("Error: {}", e);
-        if let Ok(true) = config().get::<bool>("notice.report_error") {
+        if matches!(e, Error::ClientError(client::Error::LoginThrottled(..) | client::Error::LoginSuspended(_))) {
+            // failed logins are alerted once by the client, reporting every skipped run would spam
+            info!("Login throttled, error not reported.");
+        } else if let Ok(true) = config().get::<bool>("notice.report_error") {
             info!("`report_error` configured as true, sending error...");
             let time = chrono::Local::now().naive_local();
             if let Err(e) = client.report_error(&format!("{}", time), &e).await {
@@ -42,6 +82,70 @@ async fn main() {
 
 async fn work(client: &client::Client) -> Result<()> {
     let mut data = data::Data::load_or_default();
+    session::restore(client, &mut data).await;
+    let result = collect(client, &mut data).await;
+    session::save(client, &mut data).await;
+    auto_apply::merge_quota(&mut data);
+    auto_apply::merge_handled(&mut data);
+    result
+}
+
+/// Whether `result` failed because logging in is throttled or suspended after failed logins
+fn login_paused(result: &Result<()>) -> bool {
+    matches!(result, Err(Error::ClientError(client::Error::LoginThrottled(..) | client::Error::LoginSuspended(_))))
+}
+
+/// Go on with the steps not needing the session when a step needing it was skipped because logging in is paused.
+/// Failed logins are alerted once by the client, so the skipped step is only logged.
+fn skip_if_login_paused(result: Result<()>) -> Result<()> {
+    if login_paused(&result) {
+        warn!("{}, skipped.", result.unwrap_err());
+        return Ok(());
+    }
+    result
+}
+
+async fn lectures(client: &client::Client, data: &mut data::Data, feed_enabled: bool) -> Result<()> {
+    info!("Getting new lectures...");
+    let old_lectures: Vec<Lecture> = match data.get("lectures") {
+        Some(lectures) => serde_json::from_str(lectures).unwrap_or(Vec::new()),
+        None => Vec::new()
+    };
+    let diff = client.get_new_lectures(old_lectures).await?;
+    if feed_enabled {
+        feed::record(data, diff.new.iter().map(feed::FeedItem::from_lecture).collect());
+    }
+    let filter = filter::LectureFilter::load("lecture.filter")?;
+    let mut new_lectures = filter.apply(&diff.new);
+    if config().get_bool("timetable.enabled").is_ok_and(|v| v) && !new_lectures.is_empty() {
+        let timetable = client.get_timetable().await?;
+        timetable.annotate(&mut new_lectures);
+        if config().get_bool("timetable.suppress_conflicts").is_ok_and(|v| v) {
+            new_lectures.retain(|lecture| lecture.conflicts.is_empty());
+        }
+    }
+    client.send_lecture(&new_lectures).await?;
+    data.set("lectures", serde_json::to_string(&diff.all).unwrap());
+    info!("{} new lecture(s) found.", diff.new.len());
+
+    if config().get_bool("caldav.enabled").is_ok_and(|v| v) {
+        let url = config().get_string("caldav.url")?;
+        let full_sync = data.get("caldav_synced") != Some(url.as_str());
+        if let Err(e) = client.sync_caldav(&diff, full_sync).await {
+            // `lectures` is already saved, so the diff is gone: sync everything next time
+            data.remove("caldav_synced");
+            return Err(e.into());
+        }
+        data.set("caldav_synced", url);
+    }
+    if config().get_bool("lecture.auto_apply.enabled").is_ok_and(|v| v) {
+        auto_apply::run(client, data, &diff.all).await?;
+    }
+    progress::push_summary_if_due(client, data).await
+}
+
+async fn collect(client: &client::Client, data: &mut data::Data) -> Result<()> {
+    let feed_enabled = config().get_bool("feed.enabled").is_ok_and(|v| v);
 
     let enabled = config().get_bool("school_notice.enabled").is_ok_and(|v| v);
     if enabled {
@@ -57,9 +161,12 @@ async fn work(client: &client::Client) -> Result<()> {
             let page = page.unwrap();
 
             let latest_notice = client.get_latest_notice_full_path(&page).await?;
-            if data.get(&page).map_or(true, |old| *old != latest_notice.url) {
+            if data.get(&page).is_none_or(|old| *old != latest_notice.url) {
                 info!("New notice found for page {}: {}, title: {}", page, latest_notice.url, latest_notice.title);
                 data.set(&page, latest_notice.url.clone());
+                if feed_enabled {
+                    feed::record(data, vec![feed::FeedItem::from_notice(&page, &latest_notice)]);
+                }
                 new_notices.push(latest_notice);
             }
         }
@@ -67,19 +174,50 @@ async fn work(client: &client::Client) -> Result<()> {
         info!("{} new notice(s) found.", new_notices.len());
     }
 
-    let enabled = config().get_bool("lecture.enabled").is_ok_and(|v| v);
-    if enabled {
-        info!("Getting new lectures...");
-        let session_id = data.get("sessionid");
-        let old_lectures: Vec<Lecture> = match data.get("lectures") {
-            Some(lectures) => serde_json::from_str(lectures).unwrap_or(Vec::new()),
-            None => Vec::new()
-        };
-        let (new_lectures, all_lectures, new_session_id) = client.get_new_lectures(old_lectures, &session_id).await?;
-        client.send_lecture(&new_lectures).await?;
-        data.set("lectures", serde_json::to_string(&all_lectures).unwrap());
-        data.set("sessionid", new_session_id);
-        info!("{} new lecture(s) found.", new_lectures.len());
+    if config().get_bool("rss.enabled").is_ok_and(|v| v) {
+        // feeds are third-party sites, their failures should not keep the rest from being checked
+        if let Err(e) = rss::run(client, data).await {
+            warn!("Failed to check RSS feeds: {}", e);
+        }
+    }
+
+    if config().get_bool("lecture.enabled").is_ok_and(|v| v) {
+        skip_if_login_paused(lectures(client, data, feed_enabled).await)?;
+    }
+
+    if config().get_bool("grade.enabled").is_ok_and(|v| v) {
+        skip_if_login_paused(grades::run(client, data).await)?;
+    }
+
+    if config().get_bool("exam.enabled").is_ok_and(|v| v) {
+        skip_if_login_paused(exams::run(client, data).await)?;
+    }
+
+    if config().get_bool("timetable.notify_changes").is_ok_and(|v| v) {
+        skip_if_login_paused(timetable::run(client, data).await)?;
+    }
+
+    if config().get_bool("message.enabled").is_ok_and(|v| v) {
+        skip_if_login_paused(messages::run(client, data).await)?;
+    }
+
+    if config().get_bool("selection.enabled").is_ok_and(|v| v) {
+        skip_if_login_paused(selection::run(client, data).await)?;
+    }
+
+    // sources without `auth` are still checked while logging in is paused
+    skip_if_login_paused(json_source::run(client, data).await)?;
+
+    if config().get_bool("calendar.enabled").is_ok_and(|v| v) {
+        let lectures: Vec<Lecture> = data.get("lectures").and_then(|lectures| serde_json::from_str(lectures).ok()).unwrap_or_default();
+        let events: Vec<calendar::Event> = lectures.iter().filter_map(calendar::Event::from_lecture)
+            .chain(exams::load(data).iter().filter_map(calendar::Event::from_exam))
+            .collect();
+        calendar::write(&events)?;
+    }
+
+    if feed_enabled {
+        feed::write(data)?;
     }
     Ok(())
 }
\ No newline at end of file
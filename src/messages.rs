@@ -0,0 +1,42 @@
+use log::info;
+
+use crate::{client::{Client, Message}, data::Data, utils::config, Result};
+
+static MAX_CONTENT_CHARS: usize = 200;
+
+/// Plain text of a possibly HTML message body, truncated for pushing
+fn summarize(message: &Message) -> String {
+    let text = scraper::Html::parse_fragment(&message.content).root_element().text().collect::<Vec<_>>().join("");
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() > MAX_CONTENT_CHARS {
+        format!("{}…", text.chars().take(MAX_CONTENT_CHARS).collect::<String>())
+    } else {
+        text
+    }
+}
+
+/// Forward messages not pushed before. Without any record (first run), only unread messages are pushed.
+pub async fn run(client: &Client, data: &mut Data) -> Result<()> {
+    info!("Getting messages...");
+    let messages = client.get_messages().await?;
+
+    let seen: Option<Vec<String>> = data.get("messages_seen").and_then(|seen| serde_json::from_str(seen).ok());
+    let unread_only = seen.is_none() || config().get_bool("message.unread_only").is_ok_and(|v| v);
+    let seen = seen.unwrap_or_default();
+    let new_messages: Vec<&Message> = messages.iter()
+        .filter(|message| !seen.contains(&message.id))
+        .filter(|message| !unread_only || !message.read)
+        .collect();
+    let ids: Vec<&String> = messages.iter().map(|message| &message.id).collect();
+    data.set("messages_seen", serde_json::to_string(&ids).unwrap());
+    info!("{} new message(s) found.", new_messages.len());
+
+    if !new_messages.is_empty() {
+        let content = new_messages.iter()
+            .map(|message| format!("## {}\n\n{}\n\n{}", message.title, message.time, summarize(message)))
+            .collect::<Vec<String>>()
+            .join("\n\n");
+        client.send_message(&format!("1系统有{}条新消息", new_messages.len()), &content).await?;
+    }
+    Ok(())
+}
\ No newline at end of file
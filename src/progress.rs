@@ -0,0 +1,73 @@
+use std::collections::{BTreeMap, HashMap};
+
+use log::info;
+
+use crate::{client::{Client, MyLecture}, data::Data, session, utils::config, Result};
+
+#[derive(Default)]
+struct LevelProgress {
+    required: u32,
+    attended: u32,
+    /// registered but not attended yet
+    pending: u32,
+}
+
+/// Progress per `classLevelName` against `lecture.requirements`
+fn compute(lectures: &[MyLecture]) -> BTreeMap<String, LevelProgress> {
+    let requirements: HashMap<String, u32> = config().get("lecture.requirements").unwrap_or_default();
+    let mut progress: BTreeMap<String, LevelProgress> = requirements.into_iter()
+        .map(|(level, required)| (level, LevelProgress { required, ..Default::default() }))
+        .collect();
+    for my_lecture in lectures {
+        let level = progress.entry(my_lecture.lecture.level.clone()).or_default();
+        if my_lecture.attended {
+            level.attended += 1;
+        } else if !my_lecture.lecture.cancelled {
+            level.pending += 1;
+        }
+    }
+    progress
+}
+
+fn render(progress: &BTreeMap<String, LevelProgress>) -> String {
+    let rows = progress.iter().map(|(level, p)| {
+        let state = if p.required == 0 { "-" } else if p.attended >= p.required { "已完成" } else { "未完成" };
+        format!("|{}|{}|{}|{}|{}|", level, p.attended, p.pending, p.required, state)
+    }).collect::<Vec<String>>().join("\n");
+    format!("|级别|已参加|已报名|要求|状态|\n|:-:|:-:|:-:|:-:|:-:|\n{}", rows)
+}
+
+async fn fetch(client: &Client) -> Result<BTreeMap<String, LevelProgress>> {
+    let lectures = client.get_my_lectures().await?;
+    info!("{} registered lecture(s) found.", lectures.len());
+    Ok(compute(&lectures))
+}
+
+/// `status` command: print lecture progress to stdout
+pub async fn status(client: &Client) -> Result<()> {
+    let mut data = Data::load_or_default();
+    session::restore(client, &mut data).await;
+    let progress = fetch(client).await;
+    session::save(client, &mut data).await;
+    println!("{}", render(&progress?));
+    Ok(())
+}
+
+/// Push lecture progress every `lecture.summary.interval_days` days
+pub async fn push_summary_if_due(client: &Client, data: &mut Data) -> Result<()> {
+    let interval = config().get_int("lecture.summary.interval_days").unwrap_or(0);
+    if interval <= 0 {
+        return Ok(());
+    }
+    let today = chrono::Local::now().date_naive();
+    let last = data.get("lecture_summary_last")
+        .and_then(|last| chrono::NaiveDate::parse_from_str(last, "%Y-%m-%d").ok());
+    if last.is_some_and(|last| (today - last).num_days() < interval) {
+        return Ok(());
+    }
+    info!("Pushing lecture progress summary...");
+    let progress = fetch(client).await?;
+    client.send_message("同济大讲堂进度汇总", &render(&progress)).await?;
+    data.set("lecture_summary_last", today.to_string());
+    Ok(())
+}
\ No newline at end of file
@@ -0,0 +1,99 @@
+use std::sync::OnceLock;
+
+use log::{warn, Log, Metadata, Record};
+use pretty_env_logger::env_logger;
+use regex::Regex;
+
+use crate::{credentials, utils::config};
+
+static REDACTED: &str = "<redacted>";
+
+/// Whether `log.unredacted` is set. Only meant for debugging the login flow on a trusted machine,
+/// since passwords, tokens and personal data will show up in the logs.
+pub fn unredacted() -> bool {
+    static UNREDACTED: OnceLock<bool> = OnceLock::new();
+    *UNREDACTED.get_or_init(|| config().get_bool("log.unredacted").is_ok_and(|v| v))
+}
+
+/// Patterns of secrets, with the replacement keeping the name of the secret
+fn patterns() -> &'static [(Regex, &'static str)] {
+    static PATTERNS: OnceLock<Vec<(Regex, &'static str)>> = OnceLock::new();
+    PATTERNS.get_or_init(|| [
+        // url query and form parameters
+        (r"(?i)\b(token|ticket|sessionid|uid|j_password|password|j_checkcode|checkcode|sms_checkcode|sendkey)=[^&\s;]+", "$1=<redacted>"),
+        // JSON fields
+        (r#"(?i)"(sessionid|token|password|mobile|phone|telephone|email|idcard|studentid|userid|username|realname)"\s*:\s*"[^"]*""#, r#""$1":"<redacted>""#),
+        // Server酱 keys in push urls
+        (r"sctapi\.ftqq\.com/[^./\s]+", "sctapi.ftqq.com/<redacted>"),
+        (r"[^./\s]+\.push\.ft07\.com", "<redacted>.push.ft07.com"),
+    ].into_iter().map(|(pattern, replacement)| (Regex::new(pattern).unwrap(), replacement)).collect())
+}
+
+/// Credentials and personal data read so far, replaced wherever they show up
+fn secrets() -> Vec<String> {
+    credentials::resolved().into_iter().filter(|secret| secret.len() >= 4).collect()
+}
+
+/// Remove secrets from `text`, unless `log.unredacted` is set
+pub fn text(text: &str) -> String {
+    if unredacted() {
+        return text.to_string();
+    }
+    let mut text = text.to_string();
+    for secret in secrets() {
+        text = text.replace(&secret, REDACTED);
+    }
+    for (regex, replacement) in patterns() {
+        text = regex.replace_all(&text, *replacement).into_owned();
+    }
+    text
+}
+
+/// Stand-in for a response body, which is full of personal data, unless `log.unredacted` is set
+pub fn body(body: &str) -> String {
+    if unredacted() {
+        return body.to_string();
+    }
+    format!("<{} bytes redacted>", body.len())
+}
+
+/// Logger passing every message through `text` before the actual logger
+struct RedactingLogger(env_logger::Logger);
+
+impl Log for RedactingLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.0.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.0.matches(record) {
+            return;
+        }
+        let message = text(&record.args().to_string());
+        self.0.log(&Record::builder()
+            .args(format_args!("{}", message))
+            .metadata(record.metadata().clone())
+            .module_path(record.module_path())
+            .file(record.file())
+            .line(record.line())
+            .build());
+    }
+
+    fn flush(&self) {
+        self.0.flush();
+    }
+}
+
+/// Initialize the timed logger configured by `RUST_LOG`, redacting every message
+pub fn init_logger() {
+    let mut builder = pretty_env_logger::formatted_timed_builder();
+    if let Ok(filters) = std::env::var("RUST_LOG") {
+        builder.parse_filters(&filters);
+    }
+    let logger = builder.build();
+    log::set_max_level(logger.filter());
+    log::set_boxed_logger(Box::new(RedactingLogger(logger))).unwrap();
+    if unredacted() {
+        warn!("`log.unredacted` is set, passwords, tokens and personal data WILL be written to the logs!");
+    }
+}
\ No newline at end of file
@@ -0,0 +1,49 @@
+use log::{info, warn};
+
+use crate::{client::{Client, Notice}, data::Data, feed, utils::config, Result};
+
+async fn check(client: &Client, data: &mut Data, url: &str, new_notices: &mut Vec<Notice>) -> Result<()> {
+    info!("Getting feed {}...", url);
+    let entries = client.get_feed_entries(url).await?;
+    let key = format!("rss.{}", url);
+    let seen: Option<Vec<String>> = data.get(&key).and_then(|seen| serde_json::from_str(seen).ok());
+    let guids: Vec<&String> = entries.iter().map(|entry| &entry.guid).collect();
+    data.set(&key, serde_json::to_string(&guids).unwrap());
+    let Some(seen) = seen else {
+        info!("{} entries of feed {} recorded on first run, not pushed.", entries.len(), url);
+        return Ok(());
+    };
+    let new_entries: Vec<Notice> = entries.into_iter().filter(|entry| !seen.contains(&entry.guid)).map(|entry| {
+        info!("New entry found in feed {}: {}, title: {}", url, entry.notice.url, entry.notice.title);
+        entry.notice
+    }).collect();
+    if config().get_bool("feed.enabled").is_ok_and(|v| v) {
+        // one Atom feed per subscribed feed, like the school notice pages
+        feed::record(data, new_entries.iter().map(|notice| feed::FeedItem::from_notice(url, notice)).collect());
+    }
+    new_notices.extend(new_entries);
+    Ok(())
+}
+
+/// Push new entries of feeds in `rss.feeds` as notices. A failing feed does not keep the others from being pushed.
+pub async fn run(client: &Client, data: &mut Data) -> Result<()> {
+    let feeds = config().get_array("rss.feeds")?;
+    let mut new_notices = Vec::new();
+    let mut result = Ok(());
+    for feed in feeds {
+        let feed = match feed.into_string() {
+            Ok(feed) => feed,
+            Err(e) => {
+                warn!("Failed to convert feed to string: {}, skipped", e);
+                continue;
+            }
+        };
+        if let Err(e) = check(client, data, &feed, &mut new_notices).await {
+            warn!("Failed to check feed {}: {}", feed, e);
+            result = Err(e);
+        }
+    }
+    client.send_notice(&new_notices).await?;
+    info!("{} new feed entries found.", new_notices.len());
+    result
+}
\ No newline at end of file
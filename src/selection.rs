@@ -0,0 +1,81 @@
+use log::info;
+
+use crate::{client::{Client, SelectionResult, SelectionRound}, data::Data, utils::{self, config}, Result};
+
+fn load_ids(data: &Data, key: &str) -> Vec<String> {
+    data.get(key).and_then(|ids| serde_json::from_str(ids).ok()).unwrap_or_default()
+}
+
+/// Push when a round opens and when it is about to close
+async fn check_rounds(client: &Client, data: &mut Data) -> Result<()> {
+    let rounds = client.get_selection_rounds().await?;
+
+    let now = chrono::Local::now().naive_local();
+    let remind_hours = config().get_int("selection.remind_before_hours").unwrap_or(24);
+    let mut opened = load_ids(data, "selection_opened");
+    let mut closing = load_ids(data, "selection_closing");
+    opened.retain(|id| rounds.iter().any(|round| round.id == *id));
+    closing.retain(|id| rounds.iter().any(|round| round.id == *id));
+
+    let mut content = Vec::new();
+    let describe = |round: &SelectionRound| format!("- {}（{} ~ {}）", round.name, round.begin, round.end);
+    for round in &rounds {
+        let (Some(begin), Some(end)) = (utils::parse_datetime(&round.begin), utils::parse_datetime(&round.end)) else {
+            info!("Failed to parse time of selection round {}, skipped.", round.name);
+            continue;
+        };
+        if now < begin || now >= end {
+            continue;
+        }
+        if !opened.contains(&round.id) {
+            opened.push(round.id.clone());
+            content.push(format!("{} 已开始", describe(round)));
+        } else if !closing.contains(&round.id) && remind_hours > 0 && end - now <= chrono::Duration::hours(remind_hours) {
+            closing.push(round.id.clone());
+            content.push(format!("{} 即将结束", describe(round)));
+        }
+    }
+    data.set("selection_opened", serde_json::to_string(&opened).unwrap());
+    data.set("selection_closing", serde_json::to_string(&closing).unwrap());
+    if !content.is_empty() {
+        client.send_message("选课轮次提醒", &content.join("\n")).await?;
+    }
+    Ok(())
+}
+
+/// Push changes in the selection results. The first results are only recorded.
+async fn check_results(client: &Client, data: &mut Data) -> Result<()> {
+    let results = client.get_selection_results().await?;
+
+    let old_results: Option<Vec<SelectionResult>> = data.get("selection_results").and_then(|results| serde_json::from_str(results).ok());
+    data.set("selection_results", serde_json::to_string(&results).unwrap());
+    let Some(old_results) = old_results else {
+        info!("{} selection result(s) recorded.", results.len());
+        return Ok(());
+    };
+
+    let mut changes = Vec::new();
+    for result in &results {
+        match old_results.iter().find(|old| old.class_code == result.class_code) {
+            None => changes.push(format!("- {}：{}", result.course_name, result.status)),
+            Some(old) if old.status != result.status => changes.push(format!("- {}：{} → {}", result.course_name, old.status, result.status)),
+            Some(_) => (),
+        }
+    }
+    for old in &old_results {
+        if !results.iter().any(|result| result.class_code == old.class_code) {
+            changes.push(format!("- {}：已退选或未选上", old.course_name));
+        }
+    }
+    info!("{} selection result change(s) found.", changes.len());
+    if !changes.is_empty() {
+        client.send_message("选课结果有变化", &changes.join("\n")).await?;
+    }
+    Ok(())
+}
+
+pub async fn run(client: &Client, data: &mut Data) -> Result<()> {
+    info!("Checking course selection...");
+    check_rounds(client, data).await?;
+    check_results(client, data).await
+}
\ No newline at end of file
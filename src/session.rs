@@ -0,0 +1,99 @@
+use aes_gcm::{Aes256Gcm, Key};
+use log::{info, warn};
+
+use crate::{client::{Client, Session}, credentials, data::Data, encryption, Result};
+
+/// Read a sensitive value from `data`, decrypting it if needed.
+/// Values that cannot be decrypted are dropped, so that the client simply logs in again.
+fn read(data: &Data, name: &str, key: &Option<Key<Aes256Gcm>>) -> Option<String> {
+    let value = data.get(name)?;
+    if !encryption::is_encrypted(value) {
+        return Some(value.to_string());
+    }
+    let Some(key) = key else {
+        warn!("`{}` is encrypted but `state.encryption` is not set, logging in again.", name);
+        return None;
+    };
+    encryption::decrypt(key, value)
+        .inspect_err(|e| warn!("Failed to decrypt `{}`: {}, logging in again.", name, e))
+        .ok()
+}
+
+/// Write a sensitive value to `data`, encrypted if `state.encryption` is set
+fn write(data: &mut Data, name: &str, value: String, key: &Option<Key<Aes256Gcm>>) {
+    data.set(name, match key {
+        Some(key) => encryption::encrypt(key, &value),
+        None => value,
+    });
+}
+
+/// Hand the cookie jar, the session and the failed logins persisted in `data` to the client.
+/// Failed logins are restored even without the encryption key.
+pub async fn restore(client: &Client, data: &mut Data) {
+    if client.session().await.is_some() {
+        return;
+    }
+    let key = encryption::key(data).await
+        .inspect_err(|e| warn!("Failed to get the state encryption key: {}, logging in again.", e))
+        .unwrap_or_default();
+    if let Some(cookies) = read(data, "cookies", &key) {
+        client.restore_cookies(&cookies);
+    }
+    if let Some(failures) = read(data, "login_failures", &key).and_then(|failures| serde_json::from_str(&failures).ok()) {
+        client.restore_login_failures(failures).await;
+    }
+    if let Some(id) = read(data, "sessionid", &key) {
+        let login_at = data.get("session_login_at").and_then(|login_at| login_at.parse().ok());
+        client.restore_session(Session { id, login_at }).await;
+    }
+}
+
+/// Persist the cookie jar, the session and the failed logins of the client into `data`
+pub async fn save(client: &Client, data: &mut Data) {
+    // kept in plain text, so that the backoff holds even when the key is unavailable (e.g. no keyring under cron).
+    // It holds no secret besides the salted fingerprint of the credentials.
+    data.set("login_failures", serde_json::to_string(&client.login_failures().await).unwrap());
+    let key = match encryption::key(data).await {
+        Ok(key) => key,
+        Err(e) => {
+            warn!("Failed to get the state encryption key: {}, session not saved.", e);
+            return;
+        },
+    };
+    write(data, "cookies", client.cookies(), &key);
+    if let Some(session) = client.session().await {
+        write(data, "sessionid", session.id, &key);
+        if let Some(login_at) = session.login_at {
+            data.set("session_login_at", login_at.to_string());
+        }
+    }
+}
+
+/// Read a line from the terminal, without echo for passwords
+async fn prompt(prompt: &'static str, hidden: bool) -> std::io::Result<String> {
+    tokio::task::spawn_blocking(move || {
+        if hidden {
+            return rpassword::prompt_password(prompt);
+        }
+        print!("{}", prompt);
+        std::io::Write::flush(&mut std::io::stdout())?;
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line).map(|_| line)
+    }).await?
+}
+
+/// `login` command: ask for the IAM credentials once, check them by logging in and store them in the keyring
+pub async fn login(client: &Client) -> Result<()> {
+    let username = prompt("统一身份认证用户名（学号）：", false).await.map_err(credentials::Error::from)?.trim().to_string();
+    let password = prompt("统一身份认证密码：", true).await.map_err(credentials::Error::from)?;
+    credentials::remember("login.username", &username);
+    credentials::remember("login.password", &password);
+    info!("Checking credentials...");
+    client.login_with(&username, &password).await?;
+    credentials::keyring_set("login.username", &username).await?;
+    credentials::keyring_set("login.password", &password).await?;
+    info!("Credentials stored in the keyring, `login.username` and `login.password` can be removed from the config.");
+    let mut data = Data::load_or_default();
+    save(client, &mut data).await;
+    Ok(())
+}
\ No newline at end of file
@@ -0,0 +1,74 @@
+use log::info;
+
+use crate::{client::{Client, CourseSession}, data::Data, Result};
+
+static WEEKDAYS: [&str; 7] = ["周一", "周二", "周三", "周四", "周五", "周六", "周日"];
+
+/// Format weeks like `1-8,10,12-16`
+fn format_weeks(weeks: &[u32]) -> String {
+    let mut weeks = weeks.to_vec();
+    weeks.sort_unstable();
+    weeks.dedup();
+    let mut ranges: Vec<(u32, u32)> = Vec::new();
+    for week in weeks {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == week => *end = week,
+            _ => ranges.push((week, week)),
+        }
+    }
+    ranges.iter()
+        .map(|(start, end)| if start == end { start.to_string() } else { format!("{}-{}", start, end) })
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+fn describe(session: &CourseSession) -> String {
+    let weekday = session.weekday.checked_sub(1).and_then(|i| WEEKDAYS.get(i as usize)).unwrap_or(&"未知");
+    let mut description = format!("{} 第{}-{}节（第{}周）", weekday, session.start_period, session.end_period, format_weeks(&session.weeks));
+    if !session.room.is_empty() {
+        description += &format!(" @{}", session.room);
+    }
+    description
+}
+
+/// Compare the current timetable with the stored snapshot and push added, removed and moved sessions.
+/// The first snapshot of a term is only recorded.
+pub async fn run(client: &Client, data: &mut Data) -> Result<()> {
+    info!("Getting timetable...");
+    let timetable = client.get_timetable().await?;
+
+    let term = timetable.term_begin.to_string();
+    let old_sessions: Option<Vec<CourseSession>> = if data.get("timetable_term") == Some(term.as_str()) {
+        data.get("timetable").and_then(|sessions| serde_json::from_str(sessions).ok())
+    } else {
+        None
+    };
+    data.set("timetable_term", term);
+    data.set("timetable", serde_json::to_string(&timetable.sessions).unwrap());
+    let Some(old_sessions) = old_sessions else {
+        info!("Timetable snapshot of {} session(s) recorded.", timetable.sessions.len());
+        return Ok(());
+    };
+
+    let mut removed: Vec<&CourseSession> = old_sessions.iter().filter(|session| !timetable.sessions.contains(session)).collect();
+    let mut added: Vec<&CourseSession> = timetable.sessions.iter().filter(|session| !old_sessions.contains(session)).collect();
+    let mut changes = Vec::new();
+    // a removed and an added session of the same course are reported as a move
+    removed.retain(|old| {
+        match added.iter().position(|new| new.course == old.course) {
+            Some(index) => {
+                let new = added.remove(index);
+                changes.push(format!("- 调整：{} {} → {}", old.course, describe(old), describe(new)));
+                false
+            },
+            None => true,
+        }
+    });
+    changes.extend(added.iter().map(|session| format!("- 新增：{} {}", session.course, describe(session))));
+    changes.extend(removed.iter().map(|session| format!("- 取消：{} {}", session.course, describe(session))));
+    info!("{} timetable change(s) found.", changes.len());
+    if !changes.is_empty() {
+        client.send_message("课表有变动", &changes.join("\n")).await?;
+    }
+    Ok(())
+}
\ No newline at end of file
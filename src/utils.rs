@@ -1,6 +1,8 @@
 use std::sync::OnceLock;
 
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
 use config::Config;
+use serde::{Deserialize, Deserializer};
 
 pub fn config() -> &'static Config {
     static CONFIG: OnceLock<Config> = OnceLock::new();
@@ -11,4 +13,100 @@ pub fn config() -> &'static Config {
             .build()
             .unwrap()
     })
+}
+
+/// Deserialize a flag which the APIs may encode as a bool, a number or a string
+pub fn deserialize_flag<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    Ok(match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::Bool(flag) => flag,
+        serde_json::Value::Number(number) => number.as_i64().is_some_and(|n| n != 0),
+        serde_json::Value::String(text) => matches!(text.as_str(), "1" | "true" | "是"),
+        _ => false,
+    })
+}
+
+/// Deserialize a value which the APIs may encode as either a string or a number
+pub fn deserialize_string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    Ok(match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::String(text) => text,
+        serde_json::Value::Null => String::new(),
+        value => value.to_string(),
+    })
+}
+
+/// Deserialize a count which the APIs may encode as a number, a string or `null`, `None` if it is not a number
+pub fn deserialize_count<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u32>, D::Error> {
+    Ok(match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::Number(number) => number.as_u64().and_then(|n| u32::try_from(n).ok()),
+        serde_json::Value::String(text) => text.trim().parse().ok(),
+        _ => None,
+    })
+}
+
+/// Parse a time like `2024-09-20 18:30-20:00` into (start, end), using `default_minutes` as duration
+/// when no end time is given. Returns `None` if there is no parsable date or time.
+pub fn parse_time_range(text: &str, default_minutes: i64) -> Option<(NaiveDateTime, NaiveDateTime)> {
+    let (date, rest) = text.trim().split_once(' ')?;
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+    let mut times = rest
+        .split(|c: char| !(c.is_ascii_digit() || c == ':'))
+        .filter_map(|t| NaiveTime::parse_from_str(t, "%H:%M:%S")
+            .or_else(|_| NaiveTime::parse_from_str(t, "%H:%M"))
+            .ok());
+    let start = date.and_time(times.next()?);
+    let end = times.next()
+        .map(|end| date.and_time(end))
+        .filter(|end| *end > start)
+        .unwrap_or(start + chrono::Duration::minutes(default_minutes));
+    Some((start, end))
+}
+
+/// Parse a local time given as `%Y-%m-%d %H:%M:%S` or as milliseconds since epoch
+pub fn parse_datetime(text: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(text.trim(), "%Y-%m-%d %H:%M:%S").ok()
+        .or_else(|| text.trim().parse::<i64>().ok()
+            .and_then(chrono::DateTime::from_timestamp_millis)
+            .map(|time| time.with_timezone(&chrono::Local).naive_local()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn deserialize_tolerant_count() {
+        #[derive(Deserialize)]
+        struct Count(#[serde(deserialize_with = "deserialize_count")] Option<u32>);
+        let count = |json: &str| serde_json::from_str::<Count>(json).unwrap().0;
+        assert_eq!(count("12"), Some(12));
+        assert_eq!(count(r#""12""#), Some(12));
+        assert_eq!(count("null"), None);
+        assert_eq!(count(r#""""#), None);
+        assert_eq!(count("-1"), None);
+    }
+
+    #[test]
+    fn parse_time_range_with_end() {
+        assert_eq!(parse_time_range("2024-09-20 18:30-20:00", 120), Some((time("2024-09-20 18:30"), time("2024-09-20 20:00"))));
+        assert_eq!(parse_time_range("2024-09-20 18:30:00~20:00:00", 120), Some((time("2024-09-20 18:30"), time("2024-09-20 20:00"))));
+    }
+
+    #[test]
+    fn parse_time_range_default_duration() {
+        assert_eq!(parse_time_range("2024-09-20 18:30", 90), Some((time("2024-09-20 18:30"), time("2024-09-20 20:00"))));
+        // an end before the start is ignored
+        assert_eq!(parse_time_range("2024-09-20 18:30-08:00", 60), Some((time("2024-09-20 18:30"), time("2024-09-20 19:30"))));
+    }
+
+    #[test]
+    fn parse_time_range_invalid() {
+        assert_eq!(parse_time_range("2024-09-20", 120), None);
+        assert_eq!(parse_time_range("2024-09-20 待定", 120), None);
+        assert_eq!(parse_time_range("第5周 18:30", 120), None);
+        assert_eq!(parse_time_range("", 120), None);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,91 @@
+use std::time::Duration;
+
+use log::{debug, info, warn};
+use rand::Rng;
+
+use crate::{auto_apply, client::Client, constants, data::Data, session, utils::config, Result};
+
+/// Keep polling the lectures in `watch.lectures` and push when seats become available.
+/// Errors during a single check are logged and the next check goes on.
+pub async fn run(client: &Client) -> Result<()> {
+    let interval = config().get_int("watch.interval")
+        .unwrap_or(constants::WATCH_DEFAULT_INTERVAL_SECS)
+        .max(constants::WATCH_MIN_INTERVAL_SECS) as u64;
+    info!("Watching lectures every {} seconds...", interval);
+    loop {
+        if let Err(e) = check(client).await {
+            warn!("Failed to check watched lectures: {}", e);
+        }
+        // add some jitter so that requests are not sent at a fixed rhythm
+        let jitter = rand::thread_rng().gen_range(0..=interval / 10);
+        tokio::time::sleep(Duration::from_secs(interval + jitter)).await;
+    }
+}
+
+async fn check(client: &Client) -> Result<()> {
+    let watched: Vec<String> = config().get_array("watch.lectures")
+        .map(|array| array.into_iter().filter_map(|v| v.into_string().ok()).collect())
+        .unwrap_or_default();
+    if watched.is_empty() {
+        info!("No lecture in watch list.");
+        return Ok(());
+    }
+    let mut data = Data::load_or_default();
+    // the rest is saved by scheduled runs, which may have changed it meanwhile.
+    // The session stays in the memory of the client between checks.
+    data.own_only(&["watch_available", "auto_apply_date", "auto_apply_count", "auto_apply_handled"]);
+    session::restore(client, &mut data).await;
+    let result = check_lectures(client, &mut data, &watched).await;
+    auto_apply::merge_quota(&mut data);
+    auto_apply::merge_handled(&mut data);
+    result
+}
+
+async fn check_lectures(client: &Client, data: &mut Data, watched: &[String]) -> Result<()> {
+    let lectures = client.get_lectures().await?;
+
+    // lectures already known to have seats, so that we only push once until they are full again
+    let mut available: Vec<String> = data.get("watch_available")
+        .and_then(|available| serde_json::from_str(available).ok())
+        .unwrap_or_default();
+    available.retain(|id| watched.contains(id));
+    for id in watched {
+        let Some(lecture) = lectures.iter().find(|lecture| lecture.id == *id) else {
+            debug!("Watched lecture(id = {}) not in lecture list.", id);
+            continue;
+        };
+        match lecture.remaining() {
+            None => {
+                warn!("Capacity of watched lecture(id = {}) unknown, treated as full.", id);
+                available.retain(|available_id| available_id != id);
+            },
+            Some(0) => {
+                debug!("Watched lecture(id = {}) still full.", id);
+                available.retain(|available_id| available_id != id);
+            },
+            Some(_) if available.contains(id) => (),
+            Some(remaining) => {
+                info!("{} seat(s) available for watched lecture(id = {}).", remaining, id);
+                available.push(id.clone());
+                let mut content = format!("{}（{}）有{}个空余名额", lecture.title, lecture.time, remaining);
+                if config().get_bool("watch.auto_apply").is_ok_and(|v| v) && config().get_bool("lecture.auto_apply.enabled").is_ok_and(|v| v) {
+                    if config().get_bool("lecture.auto_apply.dry_run").unwrap_or(true) {
+                        info!("[dry run] Would apply for watched lecture(id = {}): {}", id, lecture.title);
+                        content += "，试运行模式，未实际报名";
+                    } else if auto_apply::take_quota(data) {
+                        let result = client.apply_lecture(lecture).await?;
+                        if result.is_final() {
+                            auto_apply::record_handled(data, id);
+                        } else {
+                            auto_apply::refund_quota(data);
+                        }
+                        content += &if result.success { "，已自动报名成功".to_string() } else { format!("，自动报名失败：{}", result.msg) };
+                    }
+                }
+                client.send_message("关注的同济大讲堂有空余名额", &content).await?;
+            }
+        }
+    }
+    data.set("watch_available", serde_json::to_string(&available).unwrap());
+    Ok(())
+}
\ No newline at end of file